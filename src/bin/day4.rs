@@ -1,65 +1,154 @@
 use std::io;
 use std::io::Read;
 use std::str::FromStr;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
 
-#[derive(Debug,Default)]
+#[derive(Debug,Default,Clone)]
 pub struct BoardSquare {
     num: u64,
     marked: bool,
 }
 
-#[derive(Debug)]
-pub struct Board ([[BoardSquare; 5]; 5]);
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub enum WinRule {
+    Rows,
+    Columns,
+    // both the main diagonal and the anti-diagonal, only on square boards
+    Diagonals,
+    Corners,
+    FullCard,
+    // squares that all need to be marked, has to be the same size as the board
+    Pattern(Vec<Vec<bool>>),
+}
 
-impl Board {
-    fn mark(&mut self, num: u64) {
-        for row in 0..5 {
-            for col in 0..5 {
-                if self.0[row][col].num == num {
-                    self.0[row][col].marked = true;
-                }
-            }
+pub const STANDARD_RULES: &[WinRule] = &[WinRule::Rows, WinRule::Columns];
+
+impl FromStr for WinRule {
+    type Err = ();
+
+    // Either the name of a built-in rule, or a pattern like "1...1\n.1.1.\n..1.."
+    // where 1 or # is a square that has to be marked and 0 or . is one that doesn't matter.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "rows" => return Ok(WinRule::Rows),
+            "columns" => return Ok(WinRule::Columns),
+            "diagonals" => return Ok(WinRule::Diagonals),
+            "corners" => return Ok(WinRule::Corners),
+            "full" => return Ok(WinRule::FullCard),
+            _ => {},
         }
-    }
 
-    fn wins(&self) -> bool {
-        for row in 0..5 {
-            let mut win = true;
-            for col in 0..5 {
-                if !self.0[row][col].marked {
-                    win = false;
-                }
-            }
-            if win {
-                return true;
+        let mut grid = Vec::new();
+        for line in s.lines() {
+            let row = line.chars()
+                          .filter(|c| !c.is_whitespace())
+                          .map(|c| match c {
+                              '1' | '#' => Ok(true),
+                              '0' | '.' => Ok(false),
+                              _ => Err(()),
+                          })
+                          .collect::<Result<Vec<bool>, ()>>()?;
+            if !row.is_empty() {
+                grid.push(row);
             }
         }
 
-        for col in 0..5 {
-            let mut win = true;
-            for row in 0..5 {
-                if !self.0[row][col].marked {
-                    win = false;
-                }
-            }
-            if win {
-                return true;
+        if grid.is_empty() || grid.iter().any(|row| row.len() != grid[0].len()) {
+            return Err(());
+        }
+        if !grid.iter().flatten().any(|&square| square) {
+            return Err(());
+        }
+        Ok(WinRule::Pattern(grid))
+    }
+}
+
+impl WinRule {
+    // All sets of squares that win on their own once every square in them is marked.
+    fn lines(&self, rows: usize, cols: usize) -> Vec<Vec<(usize, usize)>> {
+        let lines: Vec<Vec<(usize, usize)>> = match self {
+            WinRule::Rows => (0..rows).map(|row| (0..cols).map(|col| (row, col)).collect())
+                                      .collect(),
+            WinRule::Columns => (0..cols).map(|col| (0..rows).map(|row| (row, col)).collect())
+                                         .collect(),
+            WinRule::Diagonals if rows == cols => vec![
+                (0..rows).map(|i| (i, i)).collect(),
+                (0..rows).map(|i| (i, cols - 1 - i)).collect(),
+            ],
+            WinRule::Diagonals => Vec::new(),
+            WinRule::Corners => vec![
+                vec![(0, 0), (0, cols - 1), (rows - 1, 0), (rows - 1, cols - 1)]
+            ],
+            WinRule::FullCard => vec![
+                (0..rows).flat_map(|row| (0..cols).map(move |col| (row, col))).collect()
+            ],
+            WinRule::Pattern(grid) if grid.len() == rows && grid[0].len() == cols => vec![
+                (0..rows).flat_map(|row| (0..cols).map(move |col| (row, col)))
+                         .filter(|&(row, col)| grid[row][col])
+                         .collect()
+            ],
+            WinRule::Pattern(_) => Vec::new(),
+        };
+        // a line without squares would be won before anything is marked
+        lines.into_iter().filter(|line| !line.is_empty()).collect()
+    }
+}
+
+#[derive(Debug,Clone)]
+pub struct Board (Vec<Vec<BoardSquare>>);
+
+impl Board {
+    fn rows(&self) -> usize {
+        self.0.len()
+    }
+
+    fn cols(&self) -> usize {
+        self.0[0].len()
+    }
+
+    fn mark(&mut self, num: u64) {
+        for square in self.0.iter_mut().flatten() {
+            if square.num == num {
+                square.marked = true;
             }
         }
+    }
 
-        return false;
+    // lines from WinRule::lines for the size of this board
+    fn wins(&self, lines: &[Vec<(usize, usize)>]) -> bool {
+        lines.iter().any(|line| line.iter().all(|&(row, col)| self.0[row][col].marked))
     }
 
     fn sum_unmarked(&self) -> u64 {
-        let mut sum = 0;
-        for col in 0..5 {
-            for row in 0..5 {
-                if !self.0[row][col].marked {
-                    sum += self.0[row][col].num;
-                }
+        self.0.iter()
+              .flatten()
+              .filter(|square| !square.marked)
+              .map(|square| square.num)
+              .sum()
+    }
+}
+
+impl FromStr for Board {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rows = Vec::new();
+        for line in s.lines() {
+            let row = line.split_whitespace()
+                          .map(|n| n.parse().map(|num| BoardSquare{num, marked: false}))
+                          .collect::<Result<Vec<BoardSquare>, _>>()
+                          .map_err(|_| ())?;
+            if !row.is_empty() {
+                rows.push(row);
             }
         }
-        return sum;
+
+        if rows.is_empty() || rows.iter().any(|row| row.len() != rows[0].len()) {
+            return Err(());
+        }
+        Ok(Board(rows))
     }
 }
 
@@ -69,61 +158,470 @@ pub struct Game {
     boards: Vec<Board>,
 }
 
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub enum Problem {
+    // positions are (row, col) on the board
+    DuplicateOnBoard { board: usize, number: u64, positions: Vec<(usize, usize)> },
+    // positions are indices in the draw list
+    RepeatedDraw { number: u64, draws: Vec<usize> },
+    UnknownDraw { number: u64, draw: usize },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Problem::DuplicateOnBoard { board, number, positions } =>
+                write!(f, "board {} has {} more than once, at (row, col) {:?}", board, number, positions),
+            Problem::RepeatedDraw { number, draws } =>
+                write!(f, "{} is drawn more than once, at draws {:?}", number, draws),
+            Problem::UnknownDraw { number, draw } =>
+                write!(f, "{} (draw {}) isn't on any board", number, draw),
+        }
+    }
+}
+
+impl Game {
+    // Everything that makes a game ambiguous: Board::mark marks every copy of a number,
+    // and repeated or unknown draws are wasted.
+    pub fn validate(&self) -> Vec<Problem> {
+        let mut problems = Vec::new();
+
+        for (i, b) in self.boards.iter().enumerate() {
+            let mut positions: HashMap<u64, Vec<(usize, usize)>> = HashMap::new();
+            for (row, squares) in b.0.iter().enumerate() {
+                for (col, square) in squares.iter().enumerate() {
+                    positions.entry(square.num).or_default().push((row, col));
+                }
+            }
+            let mut duplicates: Vec<(u64, Vec<(usize, usize)>)> =
+                positions.into_iter().filter(|(_, p)| p.len() > 1).collect();
+            duplicates.sort_by_key(|(_, p)| p[0]);
+            for (number, positions) in duplicates {
+                problems.push(Problem::DuplicateOnBoard { board: i, number, positions });
+            }
+        }
+
+        let mut draws: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (draw, &n) in self.numbers.iter().enumerate() {
+            draws.entry(n).or_default().push(draw);
+        }
+        let mut repeated: Vec<(u64, Vec<usize>)> =
+            draws.into_iter().filter(|(_, d)| d.len() > 1).collect();
+        repeated.sort_by_key(|(_, d)| d[0]);
+        for (number, draws) in repeated {
+            problems.push(Problem::RepeatedDraw { number, draws });
+        }
+
+        let on_boards: HashSet<u64> = self.boards.iter()
+                                                 .flat_map(|b| b.0.iter().flatten())
+                                                 .map(|s| s.num)
+                                                 .collect();
+        for (draw, &number) in self.numbers.iter().enumerate() {
+            if !on_boards.contains(&number) {
+                problems.push(Problem::UnknownDraw { number, draw });
+            }
+        }
+
+        problems
+    }
+
+    // Strict mode, for when a game has to be unambiguous (e.g. generated tournaments).
+    pub fn strict(self) -> Result<Game, Vec<Problem>> {
+        let problems = self.validate();
+        if problems.is_empty() {
+            Ok(self)
+        } else {
+            Err(problems)
+        }
+    }
+}
+
+// Splits on blank lines, where a line with only whitespace counts as blank too.
+fn blocks(s: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    for line in s.lines() {
+        if line.trim().is_empty() {
+            if !current.is_empty() {
+                blocks.push(current.join("\n"));
+                current.clear();
+            }
+        } else {
+            current.push(line);
+        }
+    }
+    if !current.is_empty() {
+        blocks.push(current.join("\n"));
+    }
+    blocks
+}
 
 impl FromStr for Game {
     type Err = ();
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let lines: Vec<&str> = s.lines().collect();
-        
-        let numbers: Vec<u64> = lines[0]
-                                .split(",")
-                                .map(|x| x.parse().unwrap())
-                                .collect();
-        
-        let mut boards = Vec::new();
-        for board in lines[1..].chunks(6) {
-            let mut b: [[BoardSquare; 5]; 5] = Default::default();
-
-            for (x, nums) in board[1..].iter().enumerate() {
-                for (y, num) in nums.split_whitespace().enumerate() {
-                    let n = num.parse().unwrap();
-                    b[x][y] = BoardSquare{num: n, marked: false}
-                }
+        let blocks = blocks(s);
+        let (numbers, boards) = blocks.split_first().ok_or(())?;
+
+        let numbers: Vec<u64> = numbers
+                                .split(',')
+                                .map(|x| x.trim().parse())
+                                .collect::<Result<_, _>>()
+                                .map_err(|_| ())?;
+
+        let boards: Vec<Board> = boards.iter()
+                                       .map(|b| b.parse())
+                                       .collect::<Result<_, _>>()?;
+
+        // all boards in a game have to be the same size
+        if let Some(first) = boards.first() {
+            if boards.iter().any(|b| b.rows() != first.rows() || b.cols() != first.cols()) {
+                return Err(());
             }
-            boards.push(Board(b));
         }
 
-        return Ok(Game {
+        Ok(Game {
             numbers,
             boards,
-        });
+        })
     }
 }
 
-pub fn bingo_first(mut g: Game) -> u64 {
-    for n in g.numbers {
-        for b in &mut g.boards {
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct WinEvent {
+    pub draw: usize,
+    pub number: u64,
+    pub board: usize,
+    pub score: u64,
+}
+
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct Replay {
+    // in the order the boards won, boards winning on the same draw by board index
+    pub wins: Vec<WinEvent>,
+    pub never_won: Vec<usize>,
+}
+
+pub fn play(g: &Game, rules: &[WinRule]) -> Replay {
+    play_until(g, rules, false)
+}
+
+// With only_first, stops after the draw where the first board wins, and never_won is
+// just the boards that hadn't won by then.
+fn play_until(g: &Game, rules: &[WinRule], only_first: bool) -> Replay {
+    let mut boards = g.boards.clone();
+    let mut won = vec![false; boards.len()];
+    let mut wins = Vec::new();
+
+    // all boards have the same size, so they can share the lines
+    let lines: Vec<Vec<(usize, usize)>> = match boards.first() {
+        Some(b) => rules.iter().flat_map(|rule| rule.lines(b.rows(), b.cols())).collect(),
+        None => Vec::new(),
+    };
+
+    for (draw, &n) in g.numbers.iter().enumerate() {
+        if only_first && !wins.is_empty() {
+            break;
+        }
+        for (i, b) in boards.iter_mut().enumerate() {
+            if won[i] {
+                continue;
+            }
             b.mark(n);
-            if b.wins() {
-                return b.sum_unmarked() * n;
+            if b.wins(&lines) {
+                won[i] = true;
+                wins.push(WinEvent {
+                    draw,
+                    number: n,
+                    board: i,
+                    score: b.sum_unmarked() * n,
+                });
             }
         }
     }
-    panic!("Should have finished!");
+
+    let never_won = (0..boards.len()).filter(|&i| !won[i]).collect();
+    Replay { wins, never_won }
 }
 
-pub fn bingo_last(mut g: Game) -> u64 {
-    for n in g.numbers {       
-        for b in &mut g.boards {
-            b.mark(n);
+// Lookups to play huge tournaments quickly: where every number sits on the boards, and
+// which winning lines go through every square. Marking a square only bumps the hit
+// counters of its own lines, so with the standard rules every hit is O(1).
+pub struct BingoIndex {
+    cells: usize,
+    // number -> (board, square), sorted by board
+    squares: HashMap<u64, Vec<(usize, usize)>>,
+    lines_through: Vec<Vec<usize>>,
+    line_sizes: Vec<usize>,
+    sums: Vec<u64>,
+}
+
+impl BingoIndex {
+    pub fn new(g: &Game, rules: &[WinRule]) -> BingoIndex {
+        let (rows, cols) = match g.boards.first() {
+            Some(b) => (b.rows(), b.cols()),
+            None => (0, 0),
+        };
+        let cells = rows * cols;
+
+        // all boards have the same size, so they can share the lines
+        let mut lines_through = vec![Vec::new(); cells];
+        let mut line_sizes = Vec::new();
+        for line in rules.iter().flat_map(|rule| rule.lines(rows, cols)) {
+            for (row, col) in &line {
+                lines_through[row * cols + col].push(line_sizes.len());
+            }
+            line_sizes.push(line.len());
         }
-        if g.boards.len() == 1 {
-            return g.boards[0].sum_unmarked() * n;
+
+        let mut squares: HashMap<u64, Vec<(usize, usize)>> = HashMap::new();
+        for (i, b) in g.boards.iter().enumerate() {
+            for (square, s) in b.0.iter().flatten().enumerate() {
+                squares.entry(s.num).or_default().push((i, square));
+            }
+        }
+
+        BingoIndex {
+            cells,
+            squares,
+            lines_through,
+            line_sizes,
+            sums: g.boards.iter().map(|b| b.sum_unmarked()).collect(),
         }
-        g.boards.retain(|b| !b.wins());
     }
-    panic!("Should have finished!");
+
+    pub fn play(&self, numbers: &[u64]) -> Replay {
+        let num_boards = self.sums.len();
+        let num_lines = self.line_sizes.len();
+        let mut marked = vec![false; num_boards * self.cells];
+        let mut hits = vec![0; num_boards * num_lines];
+        let mut unmarked = self.sums.clone();
+        let mut won = vec![false; num_boards];
+        let mut wins = Vec::new();
+
+        for (draw, &n) in numbers.iter().enumerate() {
+            let squares = match self.squares.get(&n) {
+                Some(squares) => squares,
+                None => continue,
+            };
+            // a board can hold a number more than once, mark all copies before checking
+            for group in squares.chunk_by(|a, b| a.0 == b.0) {
+                let board = group[0].0;
+                if won[board] {
+                    continue;
+                }
+
+                let mut complete = false;
+                for &(_, square) in group {
+                    if marked[board * self.cells + square] {
+                        continue;
+                    }
+                    marked[board * self.cells + square] = true;
+                    unmarked[board] -= n;
+                    for &line in &self.lines_through[square] {
+                        hits[board * num_lines + line] += 1;
+                        if hits[board * num_lines + line] == self.line_sizes[line] {
+                            complete = true;
+                        }
+                    }
+                }
+
+                if complete {
+                    won[board] = true;
+                    wins.push(WinEvent {
+                        draw,
+                        number: n,
+                        board,
+                        score: unmarked[board] * n,
+                    });
+                }
+            }
+        }
+
+        let never_won = (0..num_boards).filter(|&i| !won[i]).collect();
+        Replay { wins, never_won }
+    }
+}
+
+pub fn play_indexed(g: &Game, rules: &[WinRule]) -> Replay {
+    BingoIndex::new(g, rules).play(&g.numbers)
+}
+
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct BoardAdvice {
+    pub board: usize,
+    pub win_draw: Option<usize>,
+    pub score: Option<u64>,
+}
+
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct Advice {
+    // fastest winner first, boards that never win at the end
+    pub ranking: Vec<BoardAdvice>,
+}
+
+impl Advice {
+    pub fn pick_to_win(&self) -> Option<&BoardAdvice> {
+        self.ranking.first().filter(|b| b.win_draw.is_some())
+    }
+
+    pub fn pick_to_lose(&self) -> Option<&BoardAdvice> {
+        self.ranking.last()
+    }
+
+    // How many draws the pick to win is ahead of the next board to win,
+    // so 0 is a tie and None means no other board ever wins.
+    pub fn lead(&self) -> Option<usize> {
+        match (self.ranking.first()?.win_draw, self.ranking.get(1)?.win_draw) {
+            (Some(first), Some(second)) => Some(second - first),
+            _ => None,
+        }
+    }
+
+    // How many draws the pick to lose stays behind the board that wins before it,
+    // None if the pick never wins at all (or there is nothing to compare with).
+    pub fn trail(&self) -> Option<usize> {
+        let n = self.ranking.len();
+        match (self.ranking.get(n.checked_sub(2)?)?.win_draw, self.ranking[n - 1].win_draw) {
+            (Some(before), Some(last)) => Some(last - before),
+            _ => None,
+        }
+    }
+
+    // The ranking if only the first `draws` numbers get drawn.
+    pub fn cut_short(&self, draws: usize) -> Advice {
+        let (mut ranking, mut unfinished): (Vec<BoardAdvice>, Vec<BoardAdvice>) =
+            self.ranking.iter()
+                        .cloned()
+                        .partition(|b| b.win_draw.is_some_and(|d| d < draws));
+        for b in unfinished.iter_mut() {
+            b.win_draw = None;
+            b.score = None;
+        }
+        unfinished.sort_by_key(|b| b.board);
+        ranking.append(&mut unfinished);
+        Advice { ranking }
+    }
+}
+
+impl fmt::Display for Advice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "rank board  draw  score")?;
+        for (rank, b) in self.ranking.iter().enumerate() {
+            let draw = b.win_draw.map_or("-".to_string(), |d| d.to_string());
+            let score = b.score.map_or("-".to_string(), |s| s.to_string());
+            writeln!(f, "{:>4} {:>5} {:>5} {:>6}", rank + 1, b.board, draw, score)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn advise(g: &Game, rules: &[WinRule]) -> Advice {
+    let replay = play_indexed(g, rules);
+
+    let mut ranking: Vec<BoardAdvice> = replay.wins.iter()
+        .map(|w| BoardAdvice { board: w.board, win_draw: Some(w.draw), score: Some(w.score) })
+        .collect();
+    ranking.extend(replay.never_won.iter()
+        .map(|&board| BoardAdvice { board, win_draw: None, score: None }));
+
+    Advice { ranking }
+}
+
+// Small seeded generator (splitmix64), so simulations can be reproduced without another crate.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng(seed)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    // the modulo bias is negligible for the pool sizes we shuffle
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+#[derive(Debug,Clone,PartialEq)]
+pub struct BoardOdds {
+    pub board: usize,
+    pub p_first: f64,
+    pub p_last: f64,
+    pub p_win: f64,
+    // average draw the board wins on, over the runs where it wins at all
+    pub expected_win_draw: Option<f64>,
+}
+
+// Plays `runs` games where the distinct numbers of the draw list come out in a random order.
+// Boards winning on the same draw share the first (or last) place evenly.
+pub fn monte_carlo(g: &Game, rules: &[WinRule], runs: usize, seed: u64) -> Vec<BoardOdds> {
+    assert!(runs > 0, "Need at least one run to estimate odds");
+    let index = BingoIndex::new(g, rules);
+    let mut rng = Rng::new(seed);
+
+    let mut pool = g.numbers.clone();
+    pool.sort_unstable();
+    pool.dedup();
+
+    let num_boards = g.boards.len();
+    let mut first = vec![0.0; num_boards];
+    let mut last = vec![0.0; num_boards];
+    let mut wins = vec![0; num_boards];
+    let mut draw_sums = vec![0; num_boards];
+
+    for _ in 0..runs {
+        rng.shuffle(&mut pool);
+        let replay = index.play(&pool);
+
+        if let (Some(f), Some(l)) = (replay.wins.first(), replay.wins.last()) {
+            let firsts: Vec<&WinEvent> = replay.wins.iter().filter(|w| w.draw == f.draw).collect();
+            for w in &firsts {
+                first[w.board] += 1.0 / firsts.len() as f64;
+            }
+            let lasts: Vec<&WinEvent> = replay.wins.iter().filter(|w| w.draw == l.draw).collect();
+            for w in &lasts {
+                last[w.board] += 1.0 / lasts.len() as f64;
+            }
+        }
+        for w in &replay.wins {
+            wins[w.board] += 1;
+            draw_sums[w.board] += w.draw;
+        }
+    }
+
+    (0..num_boards).map(|board| BoardOdds {
+        board,
+        p_first: first[board] / runs as f64,
+        p_last: last[board] / runs as f64,
+        p_win: wins[board] as f64 / runs as f64,
+        expected_win_draw: if wins[board] > 0 {
+            Some(draw_sums[board] as f64 / wins[board] as f64)
+        } else {
+            None
+        },
+    }).collect()
+}
+
+pub fn bingo_first(g: &Game, rules: &[WinRule]) -> u64 {
+    play_until(g, rules, true).wins.first().expect("Should have finished!").score
+}
+
+pub fn bingo_last(g: &Game, rules: &[WinRule]) -> u64 {
+    play(g, rules).wins.last().expect("Should have finished!").score
 }
 
 
@@ -134,7 +632,7 @@ fn main() {
         .read_to_string(&mut input)
         .expect("Failed to read input");
 
-    let result = bingo_last(input.parse().expect("Failed to parse"));
+    let result = bingo_last(&input.parse().expect("Failed to parse"), STANDARD_RULES);
     println!("{}", result);
 }
 
@@ -143,9 +641,7 @@ fn main() {
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_bingo() {
-        let input = "7,4,9,5,11,17,23,2,0,14,21,24,10,16,13,6,15,25,12,22,18,20,8,19,3,26,1
+    const EXAMPLE: &str = "7,4,9,5,11,17,23,2,0,14,21,24,10,16,13,6,15,25,12,22,18,20,8,19,3,26,1
 
 22 13 17 11  0
 8  2 23  4 24
@@ -164,9 +660,259 @@ mod tests {
 18  8 23 26 20
 22 11 13  6  5
 2  0 12  3  7";
-        assert_eq!(bingo_first(input.parse().unwrap()), 4512);
-        assert_eq!(bingo_last(input.parse().unwrap()), 1924);
+
+    #[test]
+    fn test_bingo() {
+        assert_eq!(bingo_first(&EXAMPLE.parse().unwrap(), STANDARD_RULES), 4512);
+        assert_eq!(bingo_last(&EXAMPLE.parse().unwrap(), STANDARD_RULES), 1924);
+    }
+
+    #[test]
+    fn test_bingo_other_sizes() {
+        let input = "  1, 2 ,3,4,5,6
+
+
+1 2 3
+4 5 6
+  \t
+4 1 2
+9 8 7
+
+";
+        let game: Game = input.parse().unwrap();
+        assert_eq!(game.boards.len(), 2);
+        assert_eq!((game.boards[0].rows(), game.boards[0].cols()), (2, 3));
+        assert_eq!(bingo_first(&input.parse().unwrap(), STANDARD_RULES), 15 * 3);
+        assert_eq!(bingo_last(&input.parse().unwrap(), STANDARD_RULES), 24 * 4);
     }
 
+    #[test]
+    fn test_bingo_bad_boards() {
+        let mixed_sizes = "1,2,3
+
+1 2
+3 4
+
+1 2 3
+4 5 6
+7 8 9";
+        assert!(mixed_sizes.parse::<Game>().is_err());
+
+        let ragged = "1,2,3
+
+1 2
+3 4 5";
+        assert!(ragged.parse::<Game>().is_err());
 
-}
\ No newline at end of file
+        let not_a_number = "1,2,x
+
+1 2
+3 4";
+        assert!(not_a_number.parse::<Game>().is_err());
+    }
+
+    fn parse_rule(s: &str) -> Result<WinRule, ()> {
+        s.parse()
+    }
+
+    #[test]
+    fn test_win_rules() {
+        let input = "5,1,9,3,7,2
+
+1 2 3
+4 5 6
+7 8 9";
+        let first = |rules: &[WinRule]| bingo_first(&input.parse().unwrap(), rules);
+        // 5, 1, 9 completes the main diagonal
+        assert_eq!(first(&[WinRule::Diagonals]), 30 * 9);
+        // 5, 1, 9, 3, 7 marks all four corners
+        assert_eq!(first(&[WinRule::Corners]), 20 * 7);
+        assert_eq!(first(&[WinRule::Rows, WinRule::Columns]), 18 * 2);
+        let cross = parse_rule("1.1\n.#.\n#.#").unwrap();
+        assert_eq!(first(&[cross]), 20 * 7);
+        let top_left = parse_rule("11.\n...\n...").unwrap();
+        assert_eq!(first(&[top_left]), 18 * 2);
+        // a pattern of the wrong size never matches
+        let small = parse_rule("1").unwrap();
+        assert_eq!(first(&[small, WinRule::Rows]), 18 * 2);
+    }
+
+    #[test]
+    fn test_full_card() {
+        let input = "1,2,3,4,5,6
+
+1 2 3
+4 5 6";
+        assert_eq!(bingo_first(&input.parse().unwrap(), &[WinRule::FullCard]), 0);
+        assert_eq!(bingo_first(&input.parse().unwrap(), &[WinRule::Diagonals, WinRule::FullCard]), 0);
+    }
+
+    #[test]
+    fn test_parse_pattern() {
+        assert_eq!(parse_rule("1 0\n0 1"),
+                   Ok(WinRule::Pattern(vec![vec![true, false], vec![false, true]])));
+        assert!(parse_rule("10\n1").is_err());
+        assert!(parse_rule("1x").is_err());
+        assert!(parse_rule("").is_err());
+        assert_eq!(parse_rule("corners"), Ok(WinRule::Corners));
+        assert_eq!(parse_rule("full"), Ok(WinRule::FullCard));
+    }
+
+    #[test]
+    fn test_play() {
+        let input = "1,2,3,4,5
+
+1 2
+3 4
+
+5 6
+7 8
+
+2 9
+1 7";
+        let game: Game = input.parse().unwrap();
+        let replay = play(&game, STANDARD_RULES);
+        assert_eq!(replay.wins, vec![
+            WinEvent { draw: 1, number: 2, board: 0, score: 7 * 2 },
+            WinEvent { draw: 1, number: 2, board: 2, score: 16 * 2 },
+        ]);
+        assert_eq!(replay.never_won, vec![1]);
+
+        // the game isn't consumed, so it can be replayed with other rules
+        let replay = play(&game, &[WinRule::FullCard]);
+        assert_eq!(replay.wins, vec![
+            WinEvent { draw: 3, number: 4, board: 0, score: 0 },
+        ]);
+        assert_eq!(replay.never_won, vec![1, 2]);
+    }
+
+    // Deterministic pseudo-random tournaments, numbers are kept small so boards share a lot.
+    fn random_game(seed: u64, num_boards: usize, size: usize) -> Game {
+        let mut rng = Rng::new(seed);
+
+        let mut input = (0..size * size * 3).map(|_| rng.below(60).to_string())
+                                            .collect::<Vec<String>>()
+                                            .join(",");
+        for _ in 0..num_boards {
+            input.push('\n');
+            for _ in 0..size {
+                input.push('\n');
+                for _ in 0..size {
+                    input.push_str(&format!("{} ", rng.below(60)));
+                }
+            }
+        }
+        input.parse().unwrap()
+    }
+
+    #[test]
+    fn test_play_indexed() {
+        let cross = parse_rule("1...1\n.1.1.\n..1..\n.1.1.\n1...1").unwrap();
+        let rule_sets = vec![
+            STANDARD_RULES.to_vec(),
+            vec![WinRule::Diagonals, WinRule::Corners],
+            vec![WinRule::FullCard],
+            vec![cross, WinRule::Rows],
+            // can't be parsed, but can still be built
+            vec![WinRule::Pattern(vec![vec![false; 5]; 5])],
+        ];
+        for seed in 0..10 {
+            let game = random_game(seed, 50, 5);
+            for rules in &rule_sets {
+                let replay = play_indexed(&game, rules);
+                assert_eq!(replay, play(&game, rules));
+                if let (Some(first), Some(last)) = (replay.wins.first(), replay.wins.last()) {
+                    assert_eq!(first.score, bingo_first(&game, rules));
+                    assert_eq!(last.score, bingo_last(&game, rules));
+                }
+            }
+        }
+
+        let empty = [WinRule::Pattern(vec![vec![false; 5]; 5])];
+        assert_eq!(play(&random_game(0, 5, 5), &empty).never_won, vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_advise() {
+        let advice = advise(&EXAMPLE.parse().unwrap(), STANDARD_RULES);
+        assert_eq!(advice.ranking, vec![
+            BoardAdvice { board: 2, win_draw: Some(11), score: Some(4512) },
+            BoardAdvice { board: 0, win_draw: Some(13), score: Some(2192) },
+            BoardAdvice { board: 1, win_draw: Some(14), score: Some(1924) },
+        ]);
+        assert_eq!(advice.pick_to_win().unwrap().board, 2);
+        assert_eq!(advice.pick_to_lose().unwrap().board, 1);
+        assert_eq!(advice.lead(), Some(2));
+        assert_eq!(advice.trail(), Some(1));
+
+        let short = advice.cut_short(14);
+        assert_eq!(short.pick_to_lose(), Some(&BoardAdvice { board: 1, win_draw: None, score: None }));
+        assert_eq!(short.trail(), None);
+        assert_eq!(advice.cut_short(11).pick_to_win(), None);
+        assert_eq!(advice.cut_short(12).lead(), None);
+        assert_eq!(advice.cut_short(12).ranking.iter().map(|b| b.board).collect::<Vec<usize>>(),
+                   vec![2, 0, 1]);
+    }
+
+    #[test]
+    fn test_monte_carlo() {
+        let input = "1,2,3,4
+
+1 2
+
+3 4";
+        let game: Game = input.parse().unwrap();
+        let odds = monte_carlo(&game, STANDARD_RULES, 2000, 42);
+        assert_eq!(odds, monte_carlo(&game, STANDARD_RULES, 2000, 42));
+        assert!(odds.iter().all(|o| o.p_win == 1.0));
+        // both boards are completely symmetric
+        assert!((odds[0].p_first - 0.5).abs() < 0.05);
+        assert!((odds[0].p_first + odds[1].p_first - 1.0).abs() < 1e-9);
+        assert!((odds[0].p_last + odds[1].p_last - 1.0).abs() < 1e-9);
+        // a board wins as soon as one of its two numbers comes out, on average at draw index 2/3
+        let expected = odds[0].expected_win_draw.unwrap();
+        assert!((expected - 2.0 / 3.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_monte_carlo_never_wins() {
+        let input = "1,2,3
+
+1 2
+
+3 4";
+        let odds = monte_carlo(&input.parse().unwrap(), &[WinRule::FullCard], 100, 7);
+        assert_eq!(odds[0].p_first, 1.0);
+        assert_eq!(odds[1].p_win, 0.0);
+        assert_eq!(odds[1].expected_win_draw, None);
+    }
+
+    #[test]
+    fn test_validate() {
+        let input = "1,2,9,1,3,2,1
+
+1 2
+2 1
+
+3 4
+5 3";
+        let game: Game = input.parse().unwrap();
+        assert_eq!(game.validate(), vec![
+            Problem::DuplicateOnBoard { board: 0, number: 1, positions: vec![(0, 0), (1, 1)] },
+            Problem::DuplicateOnBoard { board: 0, number: 2, positions: vec![(0, 1), (1, 0)] },
+            Problem::DuplicateOnBoard { board: 1, number: 3, positions: vec![(0, 0), (1, 1)] },
+            Problem::RepeatedDraw { number: 1, draws: vec![0, 3, 6] },
+            Problem::RepeatedDraw { number: 2, draws: vec![1, 5] },
+            Problem::UnknownDraw { number: 9, draw: 2 },
+        ]);
+        assert_eq!(game.strict().unwrap_err().len(), 6);
+        assert_eq!(Problem::UnknownDraw { number: 9, draw: 2 }.to_string(),
+                   "9 (draw 2) isn't on any board");
+    }
+
+    #[test]
+    fn test_strict() {
+        let game = EXAMPLE.parse::<Game>().unwrap().strict().unwrap();
+        assert_eq!(bingo_first(&game, STANDARD_RULES), 4512);
+    }
+}
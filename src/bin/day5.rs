@@ -1,8 +1,20 @@
+#[macro_use]
+extern crate lazy_static;
+
 use regex::Regex;
 use std::io;
 use std::io::Read;
+use std::env;
+use std::fs;
+use std::path::Path;
 use std::str::FromStr;
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::VecDeque;
+use std::iter::Peekable;
+use std::str::Chars;
 
 type Coord = i32;
 
@@ -12,95 +24,931 @@ pub struct XY {
     y: Coord,
 }
 
+#[derive(Debug,Clone)]
 pub struct VentLines(Vec<VentLine>);
 
 impl FromStr for VentLines {
     type Err = ();
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(VentLines(s.lines().map(|l| l.parse().unwrap()).collect()))
+        s.lines()
+         .filter(|l| !l.trim().is_empty())
+         .map(|l| l.parse())
+         .collect::<Result<_, _>>()
+         .map(VentLines)
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum Format {
+    // the puzzle's "x1,y1 -> x2,y2"
+    Text,
+    // an array of [x1, y1, x2, y2] arrays or {"from": [x, y], "to": [x, y]} objects
+    Json,
+    // x1,y1,x2,y2 on every line, optionally with a header
+    Csv,
+}
+
+impl FromStr for Format {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            _ => Err(()),
+        }
+    }
+}
+
+impl VentLines {
+    fn parse_as(s: &str, format: Format) -> Result<VentLines, ()> {
+        match format {
+            Format::Text => s.parse(),
+            Format::Json => parse_json(s)?.into_lines(),
+            Format::Csv => parse_csv(s),
+        }
+    }
+
+    // indices of all lines going through the point
+    pub fn through(&self, p: XY, with_diagonals: bool) -> Vec<usize> {
+        self.intersecting(BoundingBox {min: p, max: p}, with_diagonals)
+    }
+
+    // indices of all lines with at least one point in the area
+    pub fn intersecting(&self, area: BoundingBox, with_diagonals: bool) -> Vec<usize> {
+        self.0.iter()
+              .enumerate()
+              .filter(|(_, line)| line.touches(&area, with_diagonals))
+              .map(|(i, _)| i)
+              .collect()
+    }
+}
+
+fn coord(n: i64) -> Result<Coord, ()> {
+    Coord::try_from(n).map_err(|_| ())
+}
+
+fn line_from_coords(coords: &[Coord]) -> Result<VentLine, ()> {
+    match *coords {
+        [x1, y1, x2, y2] => Ok(VentLine {from: XY {x: x1, y: y1}, to: XY {x: x2, y: y2}}),
+        _ => Err(()),
+    }
+}
+
+fn parse_csv(s: &str) -> Result<VentLines, ()> {
+    let rows: Vec<&str> = s.lines().filter(|l| !l.trim().is_empty()).collect();
+    // a header is a first row that has letters in it
+    let skip = match rows.first() {
+        Some(row) if row.chars().any(|c| c.is_alphabetic()) => 1,
+        _ => 0,
+    };
+
+    let mut lines = Vec::new();
+    for row in &rows[skip..] {
+        let coords = row.split(',')
+                        .map(|c| c.trim().parse())
+                        .collect::<Result<Vec<Coord>, _>>()
+                        .map_err(|_| ())?;
+        lines.push(line_from_coords(&coords)?);
+    }
+    Ok(VentLines(lines))
+}
+
+// Just enough JSON for lists of segments: no floats, booleans, nulls or escapes.
+#[derive(Debug,PartialEq)]
+enum Json {
+    Number(i64),
+    Str(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn field(&self, name: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == name).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn coords(&self) -> Result<Vec<Coord>, ()> {
+        match self {
+            Json::Array(items) => items.iter()
+                                       .map(|i| match i {
+                                           Json::Number(n) => coord(*n),
+                                           _ => Err(()),
+                                       })
+                                       .collect(),
+            _ => Err(()),
+        }
+    }
+
+    fn into_lines(self) -> Result<VentLines, ()> {
+        let segments = match self {
+            Json::Array(segments) => segments,
+            _ => return Err(()),
+        };
+
+        let mut lines = Vec::new();
+        for segment in segments {
+            let coords = match (segment.field("from"), segment.field("to")) {
+                (Some(from), Some(to)) => [from.coords()?, to.coords()?].concat(),
+                _ => segment.coords()?,
+            };
+            lines.push(line_from_coords(&coords)?);
+        }
+        Ok(VentLines(lines))
+    }
+}
+
+fn parse_json(s: &str) -> Result<Json, ()> {
+    let mut chars = s.chars().peekable();
+    let json = parse_json_value(&mut chars)?;
+    skip_whitespace(&mut chars);
+    match chars.next() {
+        None => Ok(json),
+        Some(_) => Err(()),
+    }
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+}
+
+// Parses the items of an array or object up to the closing bracket, the opening one is already gone.
+fn parse_json_items<T>(chars: &mut Peekable<Chars>, close: char,
+                       mut item: impl FnMut(&mut Peekable<Chars>) -> Result<T, ()>) -> Result<Vec<T>, ()> {
+    let mut items = Vec::new();
+    skip_whitespace(chars);
+    if chars.next_if_eq(&close).is_some() {
+        return Ok(items);
+    }
+    loop {
+        items.push(item(chars)?);
+        skip_whitespace(chars);
+        match chars.next() {
+            Some(',') => {},
+            Some(c) if c == close => return Ok(items),
+            _ => return Err(()),
+        }
+    }
+}
+
+fn parse_json_string(chars: &mut Peekable<Chars>) -> Result<String, ()> {
+    skip_whitespace(chars);
+    if chars.next() != Some('"') {
+        return Err(());
+    }
+    let mut string = String::new();
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(string),
+            Some('\\') | None => return Err(()),
+            Some(c) => string.push(c),
+        }
+    }
+}
+
+fn parse_json_value(chars: &mut Peekable<Chars>) -> Result<Json, ()> {
+    skip_whitespace(chars);
+    match chars.peek() {
+        Some('[') => {
+            chars.next();
+            parse_json_items(chars, ']', parse_json_value).map(Json::Array)
+        },
+        Some('{') => {
+            chars.next();
+            parse_json_items(chars, '}', |chars| {
+                let key = parse_json_string(chars)?;
+                skip_whitespace(chars);
+                if chars.next() != Some(':') {
+                    return Err(());
+                }
+                Ok((key, parse_json_value(chars)?))
+            }).map(Json::Object)
+        },
+        Some('"') => parse_json_string(chars).map(Json::Str),
+        Some(c) if *c == '-' || c.is_ascii_digit() => {
+            let mut number = String::new();
+            while let Some(c) = chars.next_if(|c| *c == '-' || c.is_ascii_digit()) {
+                number.push(c);
+            }
+            number.parse().map(Json::Number).map_err(|_| ())
+        },
+        _ => Err(()),
+    }
+}
+
+#[derive(Debug,Clone)]
 pub struct VentLine {
     from: XY,
     to: XY,
 }
 
 impl VentLine {
-    fn points_on_line(&self, with_diagonals: bool) -> Vec<XY> {
-        let step_x = (self.to.x - self.from.x).signum();
-        let step_y = (self.to.y - self.from.y).signum();
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox {
+            min: XY {x: self.from.x.min(self.to.x), y: self.from.y.min(self.to.y)},
+            max: XY {x: self.from.x.max(self.to.x), y: self.from.y.max(self.to.y)},
+        }
+    }
 
-        if step_x != 0 && step_y != 0 && !with_diagonals {
+    fn is_diagonal(&self) -> bool {
+        self.from.x != self.to.x && self.from.y != self.to.y
+    }
+
+    // Bresenham, so any slope works. For horizontal, vertical and 45° lines
+    // this is exactly every point between the two ends.
+    fn points_on_line(&self, with_diagonals: bool) -> Vec<XY> {
+        if self.is_diagonal() && !with_diagonals {
             return Vec::new();
         }
-        
-        let mut points = Vec::new();
-        let mut on_line = self.from;
 
-        while on_line.x != self.to.x || on_line.y != self.to.y {
-            points.push(on_line);
-            on_line.x += step_x;
-            on_line.y += step_y;
+        let mut points: Vec<XY> = self.forwards().rasterise().collect();
+        if points.first() != Some(&self.from) {
+            points.reverse();
+        }
+        points
+    }
+
+    // Bresenham rounds halfway points towards the end it started from, always
+    // walking in the same direction makes a line the same set of points both ways
+    fn forwards(&self) -> VentLine {
+        if (self.to.x, self.to.y) < (self.from.x, self.from.y) {
+            VentLine{from: self.to, to: self.from}
+        } else {
+            self.clone()
         }
-        points.push(on_line);
+    }
+
+    fn rasterise(&self) -> impl Iterator<Item = XY> {
+        // the ends can be a whole Coord range apart
+        let dx = (self.to.x as i64 - self.from.x as i64).abs();
+        let dy = -(self.to.y as i64 - self.from.y as i64).abs();
+        let step_x = self.to.x.cmp(&self.from.x) as Coord;
+        let step_y = self.to.y.cmp(&self.from.y) as Coord;
+        let mut err = dx + dy;
+
+        let to = self.to;
+        let mut on_line = Some(self.from);
+
+        std::iter::from_fn(move || {
+            let p = on_line?;
+            if p == to {
+                on_line = None;
+                return Some(p);
+            }
+            let mut next = p;
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                next.x += step_x;
+            }
+            if e2 <= dx {
+                err += dx;
+                next.y += step_y;
+            }
+            on_line = Some(next);
+            Some(p)
+        })
+    }
 
-        return points;
+    // Horizontal, vertical and 45° lines go through the area when their carrier does
+    // somewhere in the part of the area they span, other slopes are walked.
+    fn touches(&self, area: &BoundingBox, with_diagonals: bool) -> bool {
+        if self.is_diagonal() && !with_diagonals {
+            return false;
+        }
+        let Some(span) = area.intersection(&self.bounds()) else {
+            return false;
+        };
+        match Direction::of(self) {
+            Some(direction) => {
+                // the carrier is linear, so over the span it is smallest and largest in the corners
+                let corners = [(span.min.x, span.min.y), (span.min.x, span.max.y), (span.max.x, span.min.y), (span.max.x, span.max.y)]
+                    .map(|(x, y)| direction.carrier((x as i64, y as i64)));
+                let carrier = direction.carrier((self.from.x as i64, self.from.y as i64));
+                corners.iter().min().unwrap() <= &carrier && &carrier <= corners.iter().max().unwrap()
+            },
+            None => self.forwards().rasterise().any(|p| span.contains(&p)),
+        }
     }
 }
 
+lazy_static! {
+    static ref VENT_LINE: Regex =
+        Regex::new(r"^\s*(-?\d+)\s*,\s*(-?\d+)\s*->\s*(-?\d+)\s*,\s*(-?\d+)\s*$").unwrap();
+    static ref VENT_LINE_3D: Regex =
+        Regex::new(r"^\s*(-?\d+)\s*,\s*(-?\d+)\s*,\s*(-?\d+)\s*->\s*(-?\d+)\s*,\s*(-?\d+)\s*,\s*(-?\d+)\s*$").unwrap();
+}
+
 impl FromStr for VentLine {
     type Err = ();
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let re = Regex::new(r"^(\d+),(\d+) -> (\d+),(\d+)$").unwrap();
+        let caps = VENT_LINE.captures(s).ok_or(())?;
 
-        let caps = re.captures(s).unwrap();
+        let coords = (1..=4).map(|i| caps[i].parse())
+                            .collect::<Result<Vec<Coord>, _>>()
+                            .map_err(|_| ())?;
+        line_from_coords(&coords)
+    }
+}
 
-        let from_x = &caps[1].parse().unwrap();
-        let from_y = &caps[2].parse().unwrap();
-        let to_x = &caps[3].parse().unwrap();
-        let to_y = &caps[4].parse().unwrap();
+// Inclusive on both ends.
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub struct BoundingBox {
+    min: XY,
+    max: XY,
+}
 
+impl BoundingBox {
+    fn width(&self) -> usize {
+        (self.max.x - self.min.x + 1).max(0) as usize
+    }
 
-        return Ok(VentLine {
-            from: XY {x: *from_x, y: *from_y},
-            to: XY {x: *to_x, y: *to_y},
-        });
+    fn height(&self) -> usize {
+        (self.max.y - self.min.y + 1).max(0) as usize
+    }
+
+    fn contains(&self, p: &XY) -> bool {
+        self.min.x <= p.x && p.x <= self.max.x && self.min.y <= p.y && p.y <= self.max.y
+    }
+
+    fn intersection(&self, other: &BoundingBox) -> Option<BoundingBox> {
+        let min = XY {x: self.min.x.max(other.min.x), y: self.min.y.max(other.min.y)};
+        let max = XY {x: self.max.x.min(other.max.x), y: self.max.y.min(other.max.y)};
+        (min.x <= max.x && min.y <= max.y).then_some(BoundingBox {min, max})
+    }
+
+    fn grow(&self, p: &XY, margin: Coord) -> BoundingBox {
+        BoundingBox {
+            min: XY {x: self.min.x.min(p.x) - margin, y: self.min.y.min(p.y) - margin},
+            max: XY {x: self.max.x.max(p.x) + margin, y: self.max.y.max(p.y) + margin},
+        }
+    }
+
+    // row by row, like the puzzle draws it
+    fn points(&self) -> impl Iterator<Item = XY> + '_ {
+        (self.min.y..=self.max.y).flat_map(move |y| (self.min.x..=self.max.x).map(move |x| XY {x, y}))
+    }
+}
+
+// "x0,y0,x1,y1", the corners can be given in any order
+impl FromStr for BoundingBox {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let coords = s.split(',')
+                      .map(|c| c.trim().parse())
+                      .collect::<Result<Vec<Coord>, _>>()
+                      .map_err(|_| ())?;
+        match coords[..] {
+            [x0, y0, x1, y1] => Ok(BoundingBox {
+                min: XY {x: x0.min(x1), y: y0.min(y1)},
+                max: XY {x: x0.max(x1), y: y0.max(y1)},
+            }),
+            _ => Err(()),
+        }
+    }
+}
+
+// How many lines cover every point that is on at least one line.
+#[derive(Debug,Clone)]
+pub struct Coverage(HashMap<XY, u64>);
+
+impl Coverage {
+    pub fn of(lines: &VentLines, with_diagonals: bool) -> Coverage {
+        let mut field: HashMap<XY, u64> = HashMap::new();
+        for line in lines.0.iter() {
+            for p in line.points_on_line(with_diagonals) {
+                let count = field.entry(p).or_insert(0);
+                *count += 1;
+            }
+        }
+        Coverage(field)
+    }
+
+    // histogram[c] is the number of points covered by exactly c lines
+    pub fn histogram(&self) -> Vec<u64> {
+        let mut histogram = vec![0; self.max_coverage() as usize + 1];
+        for v in self.0.values() {
+            histogram[*v as usize] += 1;
+        }
+        histogram
+    }
+
+    pub fn max_coverage(&self) -> u64 {
+        self.0.values().copied().max().unwrap_or(0)
+    }
+
+    // sorted by row, then column
+    pub fn max_cells(&self) -> Vec<XY> {
+        let max = self.max_coverage();
+        let mut cells: Vec<XY> = self.0.iter()
+                                       .filter(|(_, v)| **v == max && max > 0)
+                                       .map(|(p, _)| *p)
+                                       .collect();
+        cells.sort_by_key(|p| (p.y, p.x));
+        cells
+    }
+
+    pub fn count_at_least(&self, threshold: u64) -> u64 {
+        self.0.values().filter(|v| **v >= threshold).count() as u64
+    }
+
+    pub fn get(&self, p: &XY) -> u64 {
+        self.0.get(p).copied().unwrap_or(0)
+    }
+
+    pub fn bounds(&self) -> Option<BoundingBox> {
+        let xs = || self.0.keys().map(|p| p.x);
+        let ys = || self.0.keys().map(|p| p.y);
+        Some(BoundingBox {
+            min: XY {x: xs().min()?, y: ys().min()?},
+            max: XY {x: xs().max()?, y: ys().max()?},
+        })
+    }
+
+    // The diagram from the puzzle: . where there are no lines, otherwise how many.
+    // Anything over 9 doesn't fit in one character and is drawn as #.
+    pub fn render_ascii(&self, area: BoundingBox) -> String {
+        let mut out = String::with_capacity((area.width() + 1) * area.height());
+        for (i, p) in area.points().enumerate() {
+            out.push(match self.get(&p) {
+                0 => '.',
+                c if c <= 9 => char::from_digit(c as u32, 10).unwrap(),
+                _ => '#',
+            });
+            if (i + 1) % area.width() == 0 {
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    // Binary greyscale image, white where the coverage is highest.
+    pub fn render_pgm(&self, area: BoundingBox) -> Vec<u8> {
+        let max = self.max_coverage().max(1);
+        let mut out = format!("P5\n{} {}\n255\n", area.width(), area.height()).into_bytes();
+        out.extend(area.points().map(|p| (self.get(&p) * 255 / max) as u8));
+        out
+    }
+
+    // Binary colour image, going from black through red and yellow to white.
+    pub fn render_ppm(&self, area: BoundingBox) -> Vec<u8> {
+        let max = self.max_coverage().max(1);
+        let mut out = format!("P6\n{} {}\n255\n", area.width(), area.height()).into_bytes();
+        for p in area.points() {
+            let heat = self.get(&p) * 255 * 3 / max;
+            out.extend([heat.min(255), heat.clamp(255, 510) - 255, heat.max(510) - 510].map(|c| c as u8));
+        }
+        out
+    }
+
+    pub fn is_safe(&self, p: &XY) -> bool {
+        self.get(p) < 2
+    }
+
+    // Closest safe point by Manhattan distance, searching in rings around the start.
+    // There always is one, since everything outside the lines is safe.
+    pub fn nearest_safe(&self, from: XY) -> XY {
+        for d in 0..=Coord::MAX {
+            for dx in -d..=d {
+                let dy = d - dx.abs();
+                for p in [XY {x: from.x + dx, y: from.y - dy}, XY {x: from.x + dx, y: from.y + dy}] {
+                    if self.is_safe(&p) {
+                        return p;
+                    }
+                }
+            }
+        }
+        unreachable!()
+    }
+
+    // Shortest path between two points, both included, only moving up, down, left or right
+    // through safe points. A path never needs to leave the lines' bounds by more than one.
+    pub fn safe_path(&self, from: XY, to: XY) -> Option<Vec<XY>> {
+        if !self.is_safe(&from) || !self.is_safe(&to) {
+            return None;
+        }
+        let area = self.bounds()
+                       .unwrap_or(BoundingBox {min: from, max: from})
+                       .grow(&from, 1)
+                       .grow(&to, 1);
+
+        let mut came_from: HashMap<XY, XY> = HashMap::new();
+        let mut queue = VecDeque::from([from]);
+        came_from.insert(from, from);
+        while let Some(p) = queue.pop_front() {
+            if p == to {
+                let mut path = vec![to];
+                while *path.last().unwrap() != from {
+                    path.push(came_from[path.last().unwrap()]);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+                let next = XY {x: p.x + dx, y: p.y + dy};
+                if area.contains(&next) && self.is_safe(&next) && !came_from.contains_key(&next) {
+                    came_from.insert(next, p);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
     }
 }
 
 pub fn how_many_overlaps(lines: VentLines, with_diagonals: bool) -> u64 {
-    let mut field: HashMap<XY, u64> = HashMap::new();
-    for line in lines.0.iter() {
-        for p in line.points_on_line(with_diagonals) {
-            let count = field.entry(p).or_insert(0);
-            *count += 1;
+    Coverage::of(&lines, with_diagonals).count_at_least(2)
+}
+
+
+// The directions the sweep handles without walking the lines: every such vent line lies
+// on a carrier y = c, x = c, x - y = c or x + y = c.
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+enum Direction {
+    Horizontal,
+    Vertical,
+    Diagonal,
+    AntiDiagonal,
+}
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Horizontal, Direction::Vertical, Direction::Diagonal, Direction::AntiDiagonal
+];
+
+type Point = (i64, i64);
+
+// (from, to, how many lines cover it), sorted and non-overlapping
+type Pieces = Vec<(i64, i64, u64)>;
+
+impl Direction {
+    fn of(line: &VentLine) -> Option<Direction> {
+        let dx = line.to.x as i64 - line.from.x as i64;
+        let dy = line.to.y as i64 - line.from.y as i64;
+        if dy == 0 {
+            Some(Direction::Horizontal)
+        } else if dx == 0 {
+            Some(Direction::Vertical)
+        } else if dx == dy {
+            Some(Direction::Diagonal)
+        } else if dx == -dy {
+            Some(Direction::AntiDiagonal)
+        } else {
+            None
         }
     }
 
+    // the carrier is a * x + b * y = c
+    fn coefficients(self) -> (i64, i64) {
+        match self {
+            Direction::Horizontal => (0, 1),
+            Direction::Vertical => (1, 0),
+            Direction::Diagonal => (1, -1),
+            Direction::AntiDiagonal => (1, 1),
+        }
+    }
+
+    fn carrier(self, (x, y): Point) -> i64 {
+        let (a, b) = self.coefficients();
+        a * x + b * y
+    }
+
+    // position of a point along its carrier
+    fn along(self, (x, y): Point) -> i64 {
+        match self {
+            Direction::Vertical => y,
+            _ => x,
+        }
+    }
+
+    fn point(self, carrier: i64, along: i64) -> Point {
+        match self {
+            Direction::Horizontal => (along, carrier),
+            Direction::Vertical => (carrier, along),
+            Direction::Diagonal => (along, along - carrier),
+            Direction::AntiDiagonal => (along, carrier - along),
+        }
+    }
+}
+
+// The lattice point where two carriers of different directions cross, if there is one.
+fn crossing(p: Direction, carrier_p: i64, q: Direction, carrier_q: i64) -> Option<Point> {
+    let (a1, b1) = p.coefficients();
+    let (a2, b2) = q.coefficients();
+    let det = a1 * b2 - a2 * b1;
+    let x = carrier_p * b2 - carrier_q * b1;
+    let y = a1 * carrier_q - a2 * carrier_p;
+    if x % det != 0 || y % det != 0 {
+        return None;
+    }
+    Some((x / det, y / det))
+}
+
+fn merge_intervals(mut intervals: Vec<(i64, i64)>) -> Pieces {
+    let mut events: Vec<(i64, i64)> = Vec::new();
+    for (lo, hi) in intervals.drain(..) {
+        events.push((lo, 1));
+        events.push((hi + 1, -1));
+    }
+    events.sort_unstable();
+
+    let mut pieces = Vec::new();
     let mut count = 0;
+    for (i, &(at, delta)) in events.iter().enumerate() {
+        count += delta;
+        if let Some(&(next, _)) = events.get(i + 1) {
+            if next > at && count > 0 {
+                pieces.push((at, next - 1, count as u64));
+            }
+        }
+    }
+    pieces
+}
 
-    for (_, v) in field.iter() {
-        if *v >= 2 {
+fn piece_at(pieces: &BTreeMap<i64, Pieces>, dir: Direction, p: Point) -> Option<(i64, i64, u64)> {
+    let on_carrier = pieces.get(&dir.carrier(p))?;
+    let along = dir.along(p);
+    let i = on_carrier.partition_point(|&(_, hi, _)| hi < along);
+    on_carrier.get(i).filter(|&&(lo, _, _)| lo <= along).copied()
+}
+
+// All points where pieces of direction p cross pieces of direction q. The pair is
+// sheared so p's carriers are horizontal and q's vertical, then swept left to right.
+fn crossings(p: Direction, p_pieces: &BTreeMap<i64, Pieces>,
+             q: Direction, q_pieces: &BTreeMap<i64, Pieces>,
+             found: &mut HashSet<Point>) {
+    // (u, 0 = start / 1 = query / 2 = end, v range, carrier)
+    let mut events: Vec<(i64, u8, i64, i64, i64)> = Vec::new();
+    for (&carrier, pieces) in p_pieces {
+        for &(lo, hi, _) in pieces {
+            let a = q.carrier(p.point(carrier, lo));
+            let b = q.carrier(p.point(carrier, hi));
+            events.push((a.min(b), 0, carrier, carrier, carrier));
+            events.push((a.max(b), 2, carrier, carrier, carrier));
+        }
+    }
+    for (&carrier, pieces) in q_pieces {
+        for &(lo, hi, _) in pieces {
+            let a = p.carrier(q.point(carrier, lo));
+            let b = p.carrier(q.point(carrier, hi));
+            events.push((carrier, 1, a.min(b), a.max(b), carrier));
+        }
+    }
+    events.sort_unstable();
+
+    // the pieces on one carrier don't overlap, so at most one of them is active at a time
+    let mut active: BTreeSet<i64> = BTreeSet::new();
+    for (_, kind, v_lo, v_hi, carrier) in events {
+        match kind {
+            0 => { active.insert(v_lo); },
+            2 => { active.remove(&v_lo); },
+            _ => {
+                for &p_carrier in active.range(v_lo..=v_hi) {
+                    if let Some(point) = crossing(p, p_carrier, q, carrier) {
+                        if piece_at(p_pieces, p, point).is_some() && piece_at(q_pieces, q, point).is_some() {
+                            found.insert(point);
+                        }
+                    }
+                }
+            },
+        }
+    }
+}
+
+// Number of points covered by at least `k` lines, without visiting every point.
+// Horizontal, vertical and 45° lines are merged into pieces per carrier and only the points
+// where carriers cross are looked at individually. Lines at other angles are still walked.
+pub fn count_overlaps_sweep(lines: &VentLines, with_diagonals: bool, k: u64) -> u64 {
+    assert!(k > 0, "every point is covered by at least 0 lines");
+
+    let mut intervals: Vec<BTreeMap<i64, Vec<(i64, i64)>>> = vec![BTreeMap::new(); DIRECTIONS.len()];
+    let mut other: HashMap<Point, u64> = HashMap::new();
+    for line in lines.0.iter() {
+        if line.is_diagonal() && !with_diagonals {
+            continue;
+        }
+        let from = (line.from.x as i64, line.from.y as i64);
+        let to = (line.to.x as i64, line.to.y as i64);
+        match Direction::of(line) {
+            Some(dir) => {
+                let (a, b) = (dir.along(from), dir.along(to));
+                intervals[dir as usize].entry(dir.carrier(from))
+                                       .or_default()
+                                       .push((a.min(b), a.max(b)));
+            },
+            None => {
+                for p in line.points_on_line(with_diagonals) {
+                    *other.entry((p.x as i64, p.y as i64)).or_insert(0) += 1;
+                }
+            },
+        }
+    }
+
+    let pieces: Vec<BTreeMap<i64, Pieces>> = intervals.into_iter()
+        .map(|by_carrier| by_carrier.into_iter()
+                                    .map(|(carrier, i)| (carrier, merge_intervals(i)))
+                                    .collect())
+        .collect();
+
+    // points covered by one direction only are counted straight from the pieces...
+    let mut count: i64 = pieces.iter()
+                               .flat_map(|by_carrier| by_carrier.values().flatten())
+                               .filter(|&&(_, _, c)| c >= k)
+                               .map(|&(lo, hi, _)| hi - lo + 1)
+                               .sum();
+
+    // ...and corrected for the points where several of them (or other lines) meet
+    let mut special: HashSet<Point> = other.keys().copied().collect();
+    for (i, &p) in DIRECTIONS.iter().enumerate() {
+        for &q in &DIRECTIONS[i + 1..] {
+            crossings(p, &pieces[p as usize], q, &pieces[q as usize], &mut special);
+        }
+    }
+    for point in special {
+        let mut total = other.get(&point).copied().unwrap_or(0);
+        for dir in DIRECTIONS {
+            if let Some((_, _, c)) = piece_at(&pieces[dir as usize], dir, point) {
+                total += c;
+                if c >= k {
+                    count -= 1;
+                }
+            }
+        }
+        if total >= k {
             count += 1;
         }
     }
 
-    return count;
+    count as u64
+}
+
+#[derive(Debug,Copy,Clone,PartialEq,Eq,Hash)]
+pub struct XYZ {
+    x: Coord,
+    y: Coord,
+    z: Coord,
+}
+
+#[derive(Debug,Clone)]
+pub struct VentLines3(Vec<VentLine3>);
+
+impl FromStr for VentLines3 {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.lines()
+         .filter(|l| !l.trim().is_empty())
+         .map(|l| l.parse())
+         .collect::<Result<_, _>>()
+         .map(VentLines3)
+    }
+}
+
+#[derive(Debug,Clone)]
+pub struct VentLine3 {
+    from: XYZ,
+    to: XYZ,
+}
+
+impl VentLine3 {
+    // Only lines that move the same distance along every axis they move along are supported:
+    // axis-aligned (one axis), face diagonals (two) and space diagonals (three).
+    // Any other line has no points, just like diagonals when they're not enabled.
+    fn points_on_line(&self, with_face_diagonals: bool, with_space_diagonals: bool) -> Vec<XYZ> {
+        let deltas = [self.to.x as i64 - self.from.x as i64,
+                      self.to.y as i64 - self.from.y as i64,
+                      self.to.z as i64 - self.from.z as i64];
+        let length = deltas.iter().map(|d| d.abs()).max().unwrap();
+        if deltas.iter().any(|d| *d != 0 && d.abs() != length) {
+            return Vec::new();
+        }
+
+        match deltas.iter().filter(|d| **d != 0).count() {
+            2 if !with_face_diagonals => return Vec::new(),
+            3 if !with_space_diagonals => return Vec::new(),
+            _ => {},
+        }
+
+        let [step_x, step_y, step_z] = deltas.map(|d| d.signum());
+        (0..=length).map(|i| XYZ {
+            x: (self.from.x as i64 + i * step_x) as Coord,
+            y: (self.from.y as i64 + i * step_y) as Coord,
+            z: (self.from.z as i64 + i * step_z) as Coord,
+        }).collect()
+    }
+}
+
+impl FromStr for VentLine3 {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let caps = VENT_LINE_3D.captures(s).ok_or(())?;
+
+        let c = (1..=6).map(|i| caps[i].parse())
+                       .collect::<Result<Vec<Coord>, _>>()
+                       .map_err(|_| ())?;
+        Ok(VentLine3 {
+            from: XYZ {x: c[0], y: c[1], z: c[2]},
+            to: XYZ {x: c[3], y: c[4], z: c[5]},
+        })
+    }
+}
+
+pub fn count_overlaps_3d(lines: &VentLines3, with_face_diagonals: bool, with_space_diagonals: bool,
+                         threshold: u64) -> u64 {
+    let mut field: HashMap<XYZ, u64> = HashMap::new();
+    for line in lines.0.iter() {
+        for p in line.points_on_line(with_face_diagonals, with_space_diagonals) {
+            *field.entry(p).or_insert(0) += 1;
+        }
+    }
+    field.values().filter(|v| **v >= threshold).count() as u64
 }
 
+fn render(lines: &VentLines, path: &Path, crop: Option<BoundingBox>) {
+    let coverage = Coverage::of(lines, true);
+    let area = crop.or_else(|| coverage.bounds())
+                   .unwrap_or(BoundingBox {min: XY {x: 0, y: 0}, max: XY {x: -1, y: -1}});
+
+    let image = match path.extension().and_then(|e| e.to_str()) {
+        Some("pgm") => coverage.render_pgm(area),
+        Some("ppm") => coverage.render_ppm(area),
+        _ => coverage.render_ascii(area).into_bytes(),
+    };
+    fs::write(path, image).expect("Failed to write image");
+}
 
 fn main() {
+    let mut threshold = 2;
+    let mut render_to = None;
+    let mut crop = None;
+    let mut format = None;
+    let mut three_d = false;
+    let mut face_diagonals = true;
+    let mut space_diagonals = true;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--threshold" => {
+                threshold = args.next()
+                                .and_then(|t| t.parse().ok())
+                                .filter(|t| *t > 0)
+                                .expect("--threshold needs a positive number");
+            },
+            // .pgm and .ppm files get an image, anything else the ASCII diagram
+            "--render" => {
+                render_to = Some(args.next().expect("--render needs a file name"));
+            },
+            "--format" => {
+                format = Some(args.next()
+                                  .and_then(|f| f.parse().ok())
+                                  .expect("--format needs text, json or csv"));
+            },
+            // x,y,z -> x,y,z lines, only the overlaps are counted
+            "--3d" => three_d = true,
+            // 3D only, lines like 0,0,0 -> 2,2,0 and 0,0,0 -> 2,2,2 are skipped
+            "--no-face-diagonals" => face_diagonals = false,
+            "--no-space-diagonals" => space_diagonals = false,
+            "--crop" => {
+                crop = Some(args.next()
+                                .and_then(|c| c.parse().ok())
+                                .expect("--crop needs x0,y0,x1,y1"));
+            },
+            _ => panic!("Unknown argument {}", arg),
+        }
+    }
+
     let mut input = String::new();
 
     io::stdin()
         .read_to_string(&mut input)
         .expect("Failed to read input");
 
-    let result = how_many_overlaps(input.parse().expect("Failed to parse"), true);
+    if three_d {
+        if format.is_some() || render_to.is_some() || crop.is_some() {
+            panic!("--3d only reads the text format and can't --render or --crop");
+        }
+        let lines: VentLines3 = input.parse().expect("Failed to parse");
+        println!("{}", count_overlaps_3d(&lines, face_diagonals, space_diagonals, threshold));
+        return;
+    }
+    if !face_diagonals || !space_diagonals {
+        panic!("--no-face-diagonals and --no-space-diagonals need --3d");
+    }
+
+    let lines = VentLines::parse_as(&input, format.unwrap_or(Format::Text)).expect("Failed to parse");
+    if let Some(path) = render_to {
+        render(&lines, Path::new(&path), crop);
+    }
+
+    let result = count_overlaps_sweep(&lines, true, threshold);
     println!("{}", result);
 }
 
@@ -109,6 +957,17 @@ fn main() {
 mod tests {
     use super::*;
 
+    const EXAMPLE: &str = "0,9 -> 5,9
+8,0 -> 0,8
+9,4 -> 3,4
+2,2 -> 2,1
+7,0 -> 7,4
+6,4 -> 2,0
+0,9 -> 2,9
+3,4 -> 1,4
+0,0 -> 8,8
+5,5 -> 8,2";
+
     #[test]
     fn test_on_line() {
         let line = VentLine{from: XY{x: 1, y: 1}, to: XY{x: 1, y: 3}};
@@ -123,21 +982,337 @@ mod tests {
                     vec![XY { x: 1, y: 1 }, XY { x: 2, y: 2 }, XY { x: 3, y: 3 }]);
     }
 
+    #[test]
+    fn test_on_line_directions() {
+        let points = |from: (Coord, Coord), to: (Coord, Coord)| {
+            VentLine{from: XY{x: from.0, y: from.1}, to: XY{x: to.0, y: to.1}}
+                .points_on_line(true)
+                .iter()
+                .map(|p| (p.x, p.y))
+                .collect::<Vec<(Coord, Coord)>>()
+        };
+        assert_eq!(points((3, 2), (0, 2)), vec![(3, 2), (2, 2), (1, 2), (0, 2)]);
+        assert_eq!(points((1, 3), (1, 1)), vec![(1, 3), (1, 2), (1, 1)]);
+        assert_eq!(points((9, 7), (7, 9)), vec![(9, 7), (8, 8), (7, 9)]);
+        assert_eq!(points((3, 3), (1, 1)), vec![(3, 3), (2, 2), (1, 1)]);
+        assert_eq!(points((5, 5), (5, 5)), vec![(5, 5)]);
+    }
+
+    #[test]
+    fn test_on_line_any_slope() {
+        let line = VentLine{from: XY{x: 0, y: 0}, to: XY{x: 4, y: 2}};
+        assert_eq!(line.points_on_line(false), vec![]);
+        assert_eq!(line.points_on_line(true),
+                    vec![XY { x: 0, y: 0 }, XY { x: 1, y: 1 }, XY { x: 2, y: 1 },
+                         XY { x: 3, y: 2 }, XY { x: 4, y: 2 }]);
+        let mut reversed = VentLine{from: line.to, to: line.from}.points_on_line(true);
+        reversed.reverse();
+        assert_eq!(reversed, line.points_on_line(true));
+
+        let line = VentLine{from: XY{x: 2, y: -1}, to: XY{x: -1, y: 7}};
+        let points = line.points_on_line(true);
+        assert_eq!(points.first(), Some(&line.from));
+        assert_eq!(points.last(), Some(&line.to));
+        assert_eq!(points.len(), 9);
+        // consecutive points are neighbours, so the line has no gaps
+        for pair in points.windows(2) {
+            assert!((pair[0].x - pair[1].x).abs() <= 1 && (pair[0].y - pair[1].y).abs() <= 1);
+        }
+    }
+
     #[test]
     fn test_overlaps() {
-        let input = "0,9 -> 5,9
-8,0 -> 0,8
-9,4 -> 3,4
-2,2 -> 2,1
-7,0 -> 7,4
-6,4 -> 2,0
-0,9 -> 2,9
-3,4 -> 1,4
-0,0 -> 8,8
-5,5 -> 8,2";
-        assert_eq!(how_many_overlaps(input.parse().unwrap(), false), 5);
-        assert_eq!(how_many_overlaps(input.parse().unwrap(), true), 12);
+        assert_eq!(how_many_overlaps(EXAMPLE.parse().unwrap(), false), 5);
+        assert_eq!(how_many_overlaps(EXAMPLE.parse().unwrap(), true), 12);
+    }
+
+    fn brute_force(lines: &VentLines, with_diagonals: bool, k: u64) -> u64 {
+        let mut field: HashMap<XY, u64> = HashMap::new();
+        for line in lines.0.iter() {
+            for p in line.points_on_line(with_diagonals) {
+                *field.entry(p).or_insert(0) += 1;
+            }
+        }
+        field.values().filter(|&&v| v >= k).count() as u64
+    }
+
+    // Deterministic pseudo-random numbers below the given maximum.
+    fn random(seed: u64) -> impl FnMut(u64) -> u64 {
+        let mut state = seed;
+        move |max| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (state >> 33) % max
+        }
+    }
+
+    fn random_lines(seed: u64, n: usize, any_slope: bool) -> VentLines {
+        let mut random = random(seed);
+        let mut next = |max: u64| random(max) as Coord;
+
+        let mut lines = Vec::new();
+        for _ in 0..n {
+            let from = XY{x: next(30) - 5, y: next(30) - 5};
+            let len = next(20);
+            let to = match next(if any_slope { 5 } else { 4 }) {
+                0 => XY{x: from.x + len - 10, y: from.y},
+                1 => XY{x: from.x, y: from.y + len - 10},
+                2 => XY{x: from.x + len - 10, y: from.y + len - 10},
+                3 => XY{x: from.x + len - 10, y: from.y - len + 10},
+                _ => XY{x: next(30) - 5, y: next(30) - 5},
+            };
+            lines.push(VentLine{from, to});
+        }
+        VentLines(lines)
+    }
+
+    #[test]
+    fn test_overlaps_sweep() {
+        assert_eq!(count_overlaps_sweep(&EXAMPLE.parse().unwrap(), false, 2), 5);
+        assert_eq!(count_overlaps_sweep(&EXAMPLE.parse().unwrap(), true, 2), 12);
+    }
+
+    #[test]
+    fn test_overlaps_sweep_random() {
+        for seed in 0..200 {
+            let any_slope = seed % 2 == 0;
+            let lines = random_lines(seed, 1 + (seed as usize % 40), any_slope);
+            for with_diagonals in [false, true] {
+                assert_eq!(count_overlaps_sweep(&lines, with_diagonals, 2),
+                           how_many_overlaps(lines.clone(), with_diagonals));
+                for k in [1, 3, 4] {
+                    assert_eq!(count_overlaps_sweep(&lines, with_diagonals, k),
+                               brute_force(&lines, with_diagonals, k),
+                               "seed {} k {}", seed, k);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_overlaps_sweep_long_lines() {
+        let lines: VentLines = "0,0 -> 1000000000,0
+500000000,6 -> 500000000,0
+0,5 -> 5,0
+0,0 -> 2000000000,0"
+            .parse().unwrap();
+        assert_eq!(count_overlaps_sweep(&lines, true, 2), 1000000001);
+        assert_eq!(count_overlaps_sweep(&lines, true, 3), 2);
+    }
+
+    #[test]
+    fn test_overlaps_sweep_extreme_coords() {
+        let lines: VentLines = "-2147483648,0 -> 2147483647,0
+0,2147483647 -> 0,-2147483648
+-2000000000,-2000000000 -> 2000000000,2000000000
+2000000000,-2000000000 -> -2000000000,2000000000"
+            .parse().unwrap();
+        assert_eq!(count_overlaps_sweep(&lines, false, 2), 1);
+        assert_eq!(count_overlaps_sweep(&lines, true, 4), 1);
+        assert_eq!(count_overlaps_sweep(&lines, true, 1), 4294967296 * 2 + 4000000001 * 2 - 3);
+    }
+
+    #[test]
+    fn test_coverage() {
+        let coverage = Coverage::of(&EXAMPLE.parse().unwrap(), true);
+        assert_eq!(coverage.histogram(), vec![0, 27, 10, 2]);
+        assert_eq!(coverage.max_coverage(), 3);
+        assert_eq!(coverage.max_cells(), vec![XY { x: 4, y: 4 }, XY { x: 6, y: 4 }]);
+        assert_eq!(coverage.count_at_least(1), 39);
+        assert_eq!(coverage.count_at_least(2), 12);
+        assert_eq!(coverage.count_at_least(3), 2);
+        assert_eq!(coverage.count_at_least(4), 0);
+
+        let empty = Coverage::of(&"".parse().unwrap(), true);
+        assert_eq!(empty.histogram(), vec![0]);
+        assert_eq!(empty.max_cells(), vec![]);
+    }
+
+    #[test]
+    fn test_render_ascii() {
+        let coverage = Coverage::of(&EXAMPLE.parse().unwrap(), true);
+        let area = coverage.bounds().unwrap();
+        assert_eq!(coverage.render_ascii(area), "\
+1.1....11.
+.111...2..
+..2.1.111.
+...1.2.2..
+.112313211
+...1.2....
+..1...1...
+.1.....1..
+1.......1.
+222111....
+");
+        assert_eq!(coverage.render_ascii("6,5,3,3".parse().unwrap()), "\
+1.2.
+2313
+1.2.
+");
+    }
+
+    #[test]
+    fn test_render_images() {
+        let coverage = Coverage::of(&"0,0 -> 2,0\n1,0 -> 1,1".parse().unwrap(), true);
+        let area = coverage.bounds().unwrap();
+        assert_eq!(coverage.render_pgm(area),
+                   [b"P5\n3 2\n255\n".to_vec(), vec![127, 255, 127, 0, 127, 0]].concat());
+
+        let ppm = coverage.render_ppm(area);
+        assert!(ppm.starts_with(b"P6\n3 2\n255\n"));
+        assert_eq!(ppm.len(), 11 + 3 * 6);
+        assert_eq!(&ppm[11..17], &[255, 127, 0, 255, 255, 255]);
+        assert_eq!(&ppm[20..23], &[0, 0, 0]);
+    }
+
+    #[test]
+    fn test_parse_text() {
+        let line: VentLine = "  -3,4->  5 , -6 ".parse().unwrap();
+        assert_eq!((line.from, line.to), (XY { x: -3, y: 4 }, XY { x: 5, y: -6 }));
+        assert!("1,2 -> 3".parse::<VentLine>().is_err());
+        assert!("1,2 -> 3,99999999999".parse::<VentLine>().is_err());
+        assert!("1,2 => 3,4".parse::<VentLine>().is_err());
+
+        let lines: VentLines = "1,1 -> 1,3\n\n  \n-1,2 -> 3,2\n".parse().unwrap();
+        assert_eq!(lines.0.len(), 2);
+        assert!("1,1 -> 1,3\noops".parse::<VentLines>().is_err());
+    }
+
+    #[test]
+    fn test_parse_formats() {
+        let expected = vec![(XY { x: 0, y: 9 }, XY { x: 5, y: 9 }), (XY { x: -8, y: 0 }, XY { x: 0, y: -8 })];
+        let ends = |lines: VentLines| lines.0.iter().map(|l| (l.from, l.to)).collect::<Vec<(XY, XY)>>();
+
+        let json = "[[0, 9, 5, 9], {\"from\": [-8, 0], \"to\": [0,-8]}]";
+        assert_eq!(ends(VentLines::parse_as(json, Format::Json).unwrap()), expected);
+        let json = " [ {\"to\": [5, 9], \"from\": [0, 9]},\n  [-8,0,0,-8] ] ";
+        assert_eq!(ends(VentLines::parse_as(json, Format::Json).unwrap()), expected);
+        assert_eq!(VentLines::parse_as("[]", Format::Json).unwrap().0.len(), 0);
+        assert!(VentLines::parse_as("[[0, 9, 5]]", Format::Json).is_err());
+        assert!(VentLines::parse_as("[[0, 9, 5, 9]", Format::Json).is_err());
+        assert!(VentLines::parse_as("[[0, 9, 5, 9]] x", Format::Json).is_err());
+        assert!(VentLines::parse_as("{\"from\": [0, 9]}", Format::Json).is_err());
+
+        let csv = "x1,y1,x2,y2\n0, 9, 5, 9\n-8,0,0,-8\n";
+        assert_eq!(ends(VentLines::parse_as(csv, Format::Csv).unwrap()), expected);
+        let csv = "0,9,5,9\n\n-8,0,0,-8";
+        assert_eq!(ends(VentLines::parse_as(csv, Format::Csv).unwrap()), expected);
+        assert!(VentLines::parse_as("0,9,5\n", Format::Csv).is_err());
+
+        assert_eq!(ends(VentLines::parse_as("0,9 -> 5,9\n-8,0 -> 0,-8", Format::Text).unwrap()), expected);
     }
 
+    #[test]
+    fn test_lines_queries() {
+        let lines: VentLines = EXAMPLE.parse().unwrap();
+        assert_eq!(lines.through(XY { x: 7, y: 4 }, true), vec![2, 4]);
+        assert_eq!(lines.through(XY { x: 7, y: 4 }, false), vec![2, 4]);
+        assert_eq!(lines.through(XY { x: 4, y: 4 }, true), vec![1, 2, 8]);
+        assert_eq!(lines.through(XY { x: 4, y: 4 }, false), vec![2]);
+        assert_eq!(lines.through(XY { x: 9, y: 9 }, true), vec![]);
+
+        let area = "0,0,2,2".parse().unwrap();
+        assert_eq!(lines.intersecting(area, true), vec![3, 5, 8]);
+        assert_eq!(lines.intersecting(area, false), vec![3]);
+
+        // lines far too long to list their points
+        let lines: VentLines = "0,0 -> 2000000000,0\n5,5 -> 5,-5\n-2000000000,-2000000000 -> 2000000000,2000000000\n0,1 -> 2000000000,2000000001"
+            .parse().unwrap();
+        assert_eq!(lines.through(XY { x: 5, y: 0 }, true), vec![0, 1]);
+        assert_eq!(lines.through(XY { x: 5, y: 5 }, true), vec![1, 2]);
+        assert_eq!(lines.through(XY { x: 5, y: 5 }, false), vec![1]);
+        assert_eq!(lines.intersecting("1999999999,1999999999,2000000001,2000000000".parse().unwrap(), true), vec![2, 3]);
+        assert_eq!(lines.intersecting("1999999999,-1,2000000001,1".parse().unwrap(), true), vec![0]);
+        assert_eq!(lines.intersecting("-3,-1,-1,-3".parse().unwrap(), true), vec![2]);
+
+        for seed in 0..30 {
+            let lines = random_lines(seed, 20, true);
+            for (x, y, size) in [(-6, -6, 40), (0, 0, 0), (3, 7, 2), (10, 4, 1), (20, 20, 5), (-5, 12, 3)] {
+                let area = BoundingBox {min: XY {x, y}, max: XY {x: x + size, y: y + size}};
+                for with_diagonals in [false, true] {
+                    let expected: Vec<usize> = (0..lines.0.len())
+                        .filter(|&i| lines.0[i].points_on_line(with_diagonals).iter().any(|p| area.contains(p)))
+                        .collect();
+                    assert_eq!(lines.intersecting(area, with_diagonals), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_safe_queries() {
+        let coverage = Coverage::of(&EXAMPLE.parse().unwrap(), true);
+        assert_eq!(coverage.nearest_safe(XY { x: 0, y: 0 }), XY { x: 0, y: 0 });
+        assert_eq!(coverage.nearest_safe(XY { x: 2, y: 9 }), XY { x: 2, y: 8 });
+        assert_eq!(coverage.nearest_safe(XY { x: 4, y: 4 }), XY { x: 4, y: 3 });
+
+        let path = coverage.safe_path(XY { x: 5, y: 0 }, XY { x: 5, y: 8 }).unwrap();
+        assert_eq!(path.first(), Some(&XY { x: 5, y: 0 }));
+        assert_eq!(path.last(), Some(&XY { x: 5, y: 8 }));
+        assert!(path.iter().all(|p| coverage.is_safe(p)));
+        for pair in path.windows(2) {
+            assert_eq!((pair[0].x - pair[1].x).abs() + (pair[0].y - pair[1].y).abs(), 1);
+        }
+        // row 4 is dangerous from x = 4 up to 7, so the path has to go around
+        assert_eq!(path.len(), 15);
 
-}
\ No newline at end of file
+        assert_eq!(coverage.safe_path(XY { x: 7, y: 4 }, XY { x: 0, y: 0 }), None);
+        assert_eq!(coverage.safe_path(XY { x: 1, y: 1 }, XY { x: 1, y: 1 }), Some(vec![XY { x: 1, y: 1 }]));
+
+        // fully walled in
+        let walls = Coverage::of(&"0,0 -> 4,0\n0,0 -> 4,0\n0,4 -> 4,4\n0,4 -> 4,4\n\
+                                   0,0 -> 0,4\n0,0 -> 0,4\n4,0 -> 4,4\n4,0 -> 4,4".parse().unwrap(), true);
+        assert_eq!(walls.safe_path(XY { x: 2, y: 2 }, XY { x: 9, y: 9 }), None);
+        assert_eq!(walls.nearest_safe(XY { x: 2, y: 0 }), XY { x: 2, y: -1 });
+    }
+
+    #[test]
+    fn test_on_line_3d() {
+        let points = |line: &str, face: bool, space: bool| {
+            line.parse::<VentLine3>()
+                .unwrap()
+                .points_on_line(face, space)
+                .iter()
+                .map(|p| (p.x, p.y, p.z))
+                .collect::<Vec<(Coord, Coord, Coord)>>()
+        };
+        assert_eq!(points("1,2,3 -> 1,2,1", false, false), vec![(1, 2, 3), (1, 2, 2), (1, 2, 1)]);
+        assert_eq!(points("0,0,0 -> 2,0,-2", false, true), vec![]);
+        assert_eq!(points("0,0,0 -> 2,0,-2", true, false), vec![(0, 0, 0), (1, 0, -1), (2, 0, -2)]);
+        assert_eq!(points("3,3,3 -> 1,1,1", true, false), vec![]);
+        assert_eq!(points("3,3,3 -> 1,1,1", false, true), vec![(3, 3, 3), (2, 2, 2), (1, 1, 1)]);
+        assert_eq!(points("0,0,0 -> 2,1,0", true, true), vec![]);
+        assert_eq!(points("5,5,5 -> 5,5,5", false, false), vec![(5, 5, 5)]);
+        assert_eq!(points("-2147483648,0,0 -> 2147483647,1,0", true, true), vec![]);
+        assert_eq!(points("2147483647,-2147483648,0 -> 2147483646,-2147483647,0", true, false),
+                   vec![(2147483647, -2147483648, 0), (2147483646, -2147483647, 0)]);
+        assert!("1,2 -> 3,4".parse::<VentLine3>().is_err());
+    }
+
+    #[test]
+    fn test_overlaps_3d() {
+        // the 2D example in the z = 0 plane gives the same answers
+        let flat = "0,9,0 -> 5,9,0
+8,0,0 -> 0,8,0
+9,4,0 -> 3,4,0
+2,2,0 -> 2,1,0
+7,0,0 -> 7,4,0
+6,4,0 -> 2,0,0
+0,9,0 -> 2,9,0
+3,4,0 -> 1,4,0
+0,0,0 -> 8,8,0
+5,5,0 -> 8,2,0";
+        let lines: VentLines3 = flat.parse().unwrap();
+        assert_eq!(count_overlaps_3d(&lines, false, false, 2), 5);
+        assert_eq!(count_overlaps_3d(&lines, true, false, 2), 12);
+
+        let input = "0,0,0 -> 4,4,4
+2,2,0 -> 2,2,4
+0,2,2 -> 4,2,2
+0,0,4 -> 4,4,0";
+        let lines: VentLines3 = input.parse().unwrap();
+        assert_eq!(count_overlaps_3d(&lines, false, false, 2), 1);
+        assert_eq!(count_overlaps_3d(&lines, false, true, 2), 1);
+        assert_eq!(count_overlaps_3d(&lines, false, true, 4), 1);
+        assert_eq!(count_overlaps_3d(&lines, false, false, 3), 0);
+    }
+}
@@ -1,89 +1,1194 @@
 use std::io;
 use std::io::Read;
 use std::str::FromStr;
-use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::env;
+use std::fmt;
+use std::fs;
 
 type Lifetime = u8;
 type FishCount = u128;
 
+const TIMERS: usize = 9;
+// enough for any timer a Lifetime can hold
+const MAX_TIMERS: usize = Lifetime::MAX as usize + 1;
+
+// What the number of fish is counted in: plain u128, BigCount when that isn't enough,
+// or Modular when only the count modulo some number is needed. New counts are made
+// from one that is already there, that way a Modular count knows its modulus.
+pub trait Count: Clone + PartialEq + fmt::Debug + fmt::Display {
+    // n, counted the same way as self
+    fn constant(&self, n: u64) -> Self;
+    fn add(&self, other: &Self) -> Self;
+    // other is never more than self
+    fn sub(&self, other: &Self) -> Self;
+    fn mul(&self, other: &Self) -> Self;
+    // self * percent / 100 rounded down, None when that can't be known from the count
+    fn percent(&self, percent: u32) -> Option<Self>;
+    // self - other, or 0 when other is more, None when the count can't tell which is more
+    fn sub_or_zero(&self, other: &Self) -> Option<Self>;
+}
+
+impl Count for FishCount {
+    fn constant(&self, n: u64) -> Self {
+        n as FishCount
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        self.checked_add(*other).expect("Too many fish for a u128, count them with BigCount (--exact)")
+    }
+
+    fn sub(&self, other: &Self) -> Self {
+        self - other
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        self.checked_mul(*other).expect("Too many fish for a u128, count them with BigCount (--exact)")
+    }
+
+    fn percent(&self, percent: u32) -> Option<Self> {
+        Some(self / 100 * percent as FishCount + self % 100 * percent as FishCount / 100)
+    }
+
+    fn sub_or_zero(&self, other: &Self) -> Option<Self> {
+        Some(FishCount::saturating_sub(*self, *other))
+    }
+}
+
+// Counts that are the number of fish itself, so they can be compared to each other.
+pub trait Exact: Count {}
+
+impl Exact for FishCount {}
+
+impl Exact for BigCount {}
+
+// Arbitrary precision unsigned integer: little-endian base 2^32 digits, without leading zeros.
 #[derive(Debug,Clone,PartialEq,Eq)]
-pub struct Population(HashMap<Lifetime, FishCount>);
+pub struct BigCount(Vec<u32>);
 
-impl Population {
-    fn num_fish(&self) -> FishCount {
-        self.0.values().sum()
+impl BigCount {
+    fn trimmed(mut digits: Vec<u32>) -> BigCount {
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        BigCount(digits)
+    }
+
+    // self / d and what is left over, d has to fit in a digit
+    fn div_rem(&self, d: u64) -> (BigCount, u64) {
+        let mut digits = self.0.clone();
+        let mut remainder = 0;
+        for digit in digits.iter_mut().rev() {
+            let value = (remainder << 32) | *digit as u64;
+            *digit = (value / d) as u32;
+            remainder = value % d;
+        }
+        (BigCount::trimmed(digits), remainder)
     }
 }
 
-impl FromStr for Population {
-    type Err = ();
+impl From<u64> for BigCount {
+    fn from(n: u64) -> Self {
+        BigCount::trimmed(vec![n as u32, (n >> 32) as u32])
+    }
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut map = HashMap::new();
-        for lifetime in s.split(",").map(|n| n.parse().unwrap()) {
-            let count = map.entry(lifetime).or_insert(0);
-            *count += 1;
+impl Count for BigCount {
+    fn constant(&self, n: u64) -> Self {
+        BigCount::from(n)
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        let mut digits = Vec::with_capacity(self.0.len().max(other.0.len()) + 1);
+        let mut carry = 0;
+        for i in 0..self.0.len().max(other.0.len()) {
+            let sum = *self.0.get(i).unwrap_or(&0) as u64 + *other.0.get(i).unwrap_or(&0) as u64 + carry;
+            digits.push(sum as u32);
+            carry = sum >> 32;
+        }
+        digits.push(carry as u32);
+        BigCount::trimmed(digits)
+    }
+
+    fn sub(&self, other: &Self) -> Self {
+        let mut digits = Vec::with_capacity(self.0.len());
+        let mut borrow = 0;
+        for (i, d) in self.0.iter().enumerate() {
+            let subtract = *other.0.get(i).unwrap_or(&0) as i64 + borrow;
+            let mut diff = *d as i64 - subtract;
+            borrow = 0;
+            if diff < 0 {
+                diff += 1 << 32;
+                borrow = 1;
+            }
+            digits.push(diff as u32);
+        }
+        BigCount::trimmed(digits)
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        let mut digits = vec![0u32; self.0.len() + other.0.len()];
+        for (i, a) in self.0.iter().enumerate() {
+            let mut carry = 0;
+            for (j, b) in other.0.iter().enumerate() {
+                let product = *a as u64 * *b as u64 + digits[i + j] as u64 + carry;
+                digits[i + j] = product as u32;
+                carry = product >> 32;
+            }
+            digits[i + other.0.len()] = carry as u32;
         }
-        return Ok(Population(map));
+        BigCount::trimmed(digits)
+    }
+
+    fn percent(&self, percent: u32) -> Option<Self> {
+        Some(self.mul(&BigCount::from(percent as u64)).div_rem(100).0)
+    }
+
+    fn sub_or_zero(&self, other: &Self) -> Option<Self> {
+        // without leading zeros, more digits is more
+        let less = self.0.len().cmp(&other.0.len())
+                               .then_with(|| self.0.iter().rev().cmp(other.0.iter().rev()))
+                               .is_lt();
+        Some(if less { BigCount(Vec::new()) } else { self.sub(other) })
     }
 }
 
-pub fn step(pop: &Population) -> Population {
-    let mut map = HashMap::new();
+impl fmt::Display for BigCount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // peel off 9 decimal digits at a time, lowest first
+        let mut rest = self.clone();
+        let mut parts = Vec::new();
+        while !rest.0.is_empty() {
+            let (quotient, remainder) = rest.div_rem(1_000_000_000);
+            parts.push(remainder);
+            rest = quotient;
+        }
 
-    for (lifetime, count) in pop.0.iter() {
-        match lifetime {
-            0 => {
-                *map.entry(6).or_insert(0) += count;
-                *map.entry(8).or_insert(0) += count;
+        match parts.split_last() {
+            None => write!(f, "0"),
+            Some((highest, rest)) => {
+                write!(f, "{}", highest)?;
+                for part in rest.iter().rev() {
+                    write!(f, "{:09}", part)?;
+                }
+                Ok(())
             },
-            x => *map.entry(*x - 1).or_insert(0) += count,
         }
     }
-    return Population(map);
 }
 
-pub fn simulate(pop: &Population, steps: u32) -> Population {
-    // No idea how to get rid of this clone, which seems unnecessary to me :/
-    // Probably doing something wrong with lifetimes?
-    //   Update: could be solved by
-    //    - replace .clone() with calling step once before the loop (& adjust loop to 1..steps)
-    //    - changing the design to mutate the population instead of making immutable copies
-    let mut current_pop = pop.clone();
+// A count modulo some positive number. Counts modulo different numbers don't mix.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct Modular {
+    value: u64,
+    modulus: u64,
+}
+
+impl Modular {
+    pub fn zero(modulus: u64) -> Modular {
+        assert!(modulus > 0, "Can't count modulo 0");
+        Modular { value: 0, modulus }
+    }
+
+    fn reduced(&self, value: u128) -> Modular {
+        Modular { value: (value % self.modulus as u128) as u64, modulus: self.modulus }
+    }
+
+    fn check(&self, other: &Modular) {
+        assert_eq!(self.modulus, other.modulus, "Can't mix counts modulo different numbers");
+    }
+}
+
+impl Count for Modular {
+    fn constant(&self, n: u64) -> Self {
+        self.reduced(n as u128)
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        self.check(other);
+        self.reduced(self.value as u128 + other.value as u128)
+    }
+
+    fn sub(&self, other: &Self) -> Self {
+        self.check(other);
+        self.reduced(self.value as u128 + self.modulus as u128 - other.value as u128)
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        self.check(other);
+        self.reduced(self.value as u128 * other.value as u128)
+    }
+
+    // modulo a number there's no telling what rounding down does
+    fn percent(&self, _percent: u32) -> Option<Self> {
+        None
+    }
+
+    fn sub_or_zero(&self, _other: &Self) -> Option<Self> {
+        None
+    }
+}
+
+impl fmt::Display for Modular {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.value)
+    }
+}
+
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct LifeCycle {
+    // what the timer goes back to after spawning
+    pub reset: Lifetime,
+    pub newborn: Lifetime,
+    // how many newborns a fish spawns at once
+    pub litter: u64,
+    // fish die at the end of a day when they are this many days old (or older), counting the
+    // ones from the input as 0 days old. With 0, newborns die right away and the rest after a day.
+    pub max_age: Option<u32>,
+    // the percentage of fish that die at the end of every day, newborns included, rounded down
+    // per timer, or with a maximum age, per group of fish that have had the same timer all along
+    pub mortality: Option<u32>,
+}
+
+impl Default for LifeCycle {
+    fn default() -> Self {
+        LifeCycle {
+            reset: 6,
+            newborn: 8,
+            litter: 1,
+            max_age: None,
+            mortality: None,
+        }
+    }
+}
+
+impl LifeCycle {
+    fn timers(&self) -> usize {
+        self.reset.max(self.newborn) as usize + 1
+    }
+
+    // Fish dying doesn't fit in a matrix over timers, those have to go day by day.
+    fn is_linear(&self) -> bool {
+        self.max_age.is_none() && self.mortality.is_none()
+    }
+
+    fn timer_after(&self, timer: Lifetime, days: u32) -> Lifetime {
+        if days <= timer as u32 {
+            return timer - days as Lifetime;
+        }
+        let since_reset = (days - timer as u32 - 1) % (self.reset as u32 + 1);
+        self.reset - since_reset as Lifetime
+    }
+}
+
+// Fish per timer in a ring: timer t is at counts[(head + t) % N]. A day passing only
+// moves the head along, so stepping is O(1) however many timers there are.
+#[derive(Debug,Clone)]
+pub struct Population<C: Count = FishCount, const N: usize = TIMERS> {
+    counts: [C; N],
+    head: usize,
+}
+
+impl<C: Count, const N: usize> Population<C, N> {
+    fn get(&self, timer: usize) -> &C {
+        &self.counts[(self.head + timer) % N]
+    }
+
+    fn get_mut(&mut self, timer: usize) -> &mut C {
+        &mut self.counts[(self.head + timer) % N]
+    }
+
+    fn add(&mut self, timer: Lifetime, count: &C) {
+        let current = self.get_mut(timer as usize);
+        *current = current.add(count);
+    }
+
+    fn remove(&mut self, timer: Lifetime, count: &C) {
+        let current = self.get_mut(timer as usize);
+        *current = current.sub(count);
+    }
+
+    // Like parse, for counts that can't be made from nothing, e.g. with Modular::zero(m).
+    fn parse_with(s: &str, zero: C) -> Result<Self, ()> {
+        let one = zero.constant(1);
+        let mut pop = Population {
+            counts: std::array::from_fn(|_| zero.clone()),
+            head: 0,
+        };
+        for lifetime in s.trim().split(',') {
+            let lifetime: Lifetime = lifetime.trim().parse().map_err(|_| ())?;
+            if lifetime as usize >= N {
+                return Err(());
+            }
+            pop.add(lifetime, &one);
+        }
+        Ok(pop)
+    }
+
+    fn num_fish(&self) -> C {
+        self.counts[1..].iter().fold(self.counts[0].clone(), |acc, c| acc.add(c))
+    }
+
+    // Moves the timers along, whoever dies is up to the caller.
+    fn rotate(&mut self, lc: &LifeCycle) {
+        assert!(lc.timers() <= N, "Life cycle needs more than {} timers", N);
+        // after moving the head, the fish that were at 0 are where timer N - 1 is
+        self.head = (self.head + 1) % N;
+        let zero = self.get(0).constant(0);
+        let spawning = std::mem::replace(self.get_mut(N - 1), zero);
+        self.add(lc.reset, &spawning);
+        self.add(lc.newborn, &spawning.mul(&spawning.constant(lc.litter)));
+    }
+
+    fn cull(&mut self, percent: u32) {
+        for timer in 0..N {
+            let died = dying(self.get(timer), percent);
+            self.remove(timer as Lifetime, &died);
+        }
+    }
+}
+
+fn dying<C: Count>(count: &C, percent: u32) -> C {
+    count.percent(percent).expect("Can't tell how many fish die when counting modulo a number")
+}
+
+impl<C: Count, const N: usize> PartialEq for Population<C, N> {
+    fn eq(&self, other: &Self) -> bool {
+        (0..N).all(|timer| self.get(timer) == other.get(timer))
+    }
+}
+
+impl<C: Count + Eq, const N: usize> Eq for Population<C, N> {}
+
+impl<C: Count + From<u64>, const N: usize> FromStr for Population<C, N> {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Population::parse_with(s, C::from(0))
+    }
+}
+
+// The day after the input, where every fish is 0 days old. To keep going with fish
+// that die, use Simulation, it remembers how old they are.
+pub fn step<C: Count, const N: usize>(pop: &Population<C, N>, lc: &LifeCycle) -> Population<C, N> {
+    simulate(pop.clone(), lc, 1)
+}
+
+// Steps a population one day at a time, keeping track of who dies.
+pub struct Simulation<'a, C: Count = FishCount, const N: usize = TIMERS> {
+    pop: Population<C, N>,
+    lc: &'a LifeCycle,
+    day: u32,
+    // All fish born on the same day have the same timer for the rest of their lives, so
+    // to know how many die of old age (and where they are) it's enough to know how many
+    // were born every day, for as many days back as they can live.
+    births: VecDeque<C>,
+    // the fish from the input are the only ones that weren't born during the simulation
+    initial: Vec<(Lifetime, C)>,
+    // no timer at or above this ever has fish
+    width: usize,
+}
+
+// The population at the end of a day.
+#[derive(Debug,Clone,PartialEq)]
+pub struct Day<C: Count = FishCount> {
+    pub day: u32,
+    pub total: C,
+    pub timers: Vec<C>,
+}
+
+impl<'a, C: Count, const N: usize> Simulation<'a, C, N> {
+    pub fn new(pop: Population<C, N>, lc: &'a LifeCycle) -> Self {
+        let zero = pop.get(0).constant(0);
+        let initial: Vec<(Lifetime, C)> = match lc.max_age {
+            Some(_) => (0..N).map(|t| (t as Lifetime, pop.get(t).clone())).collect(),
+            None => Vec::new(),
+        };
+        let width = (0..N).filter(|t| *pop.get(*t) != zero)
+                          .map(|t| t + 1)
+                          .fold(lc.timers(), usize::max);
+        Simulation { pop, lc, day: 0, births: VecDeque::new(), initial, width }
+    }
+
+    fn advance(&mut self) {
+        let lc = self.lc;
+        let spawning = self.pop.get(0).clone();
+        self.pop.rotate(lc);
+        self.day += 1;
+
+        if let Some(max_age) = lc.max_age {
+            self.births.push_back(spawning.mul(&spawning.constant(lc.litter)));
+            if self.births.len() > max_age as usize {
+                let died = self.births.pop_front().unwrap();
+                self.pop.remove(lc.timer_after(lc.newborn, max_age), &died);
+            }
+            // even with a maximum age of 0, nobody dies before the first day is over
+            if self.day == max_age.max(1) {
+                for (timer, count) in self.initial.drain(..) {
+                    self.pop.remove(lc.timer_after(timer, self.day), &count);
+                }
+            }
+        }
+        if let Some(percent) = lc.mortality {
+            self.cull(percent);
+        }
+    }
+
+    // With a maximum age, the fish that die have to be taken from the groups that
+    // die of old age later, or those would take away fish that are already gone.
+    fn cull(&mut self, percent: u32) {
+        let lc = self.lc;
+        if lc.max_age.is_none() {
+            self.pop.cull(percent);
+            return;
+        }
+        // the last births are from today
+        let first_born = self.day + 1 - self.births.len() as u32;
+        for (i, count) in self.births.iter_mut().enumerate() {
+            let died = dying(count, percent);
+            *count = count.sub(&died);
+            self.pop.remove(lc.timer_after(lc.newborn, self.day - first_born - i as u32), &died);
+        }
+        for (timer, count) in self.initial.iter_mut() {
+            let died = dying(count, percent);
+            *count = count.sub(&died);
+            self.pop.remove(lc.timer_after(*timer, self.day), &died);
+        }
+    }
+
+    fn summary(&self) -> Day<C> {
+        Day {
+            day: self.day,
+            total: self.pop.num_fish(),
+            timers: (0..self.width).map(|t| self.pop.get(t).clone()).collect(),
+        }
+    }
+}
+
+impl<C: Count, const N: usize> Iterator for Simulation<'_, C, N> {
+    type Item = Day<C>;
+
+    fn next(&mut self) -> Option<Day<C>> {
+        self.advance();
+        Some(self.summary())
+    }
+}
+
+pub fn simulate<C: Count, const N: usize>(pop: Population<C, N>, lc: &LifeCycle, steps: u32) -> Population<C, N> {
+    let mut sim = Simulation::new(pop, lc);
     for _ in 0..steps {
-        current_pop = step(&current_pop);
+        sim.advance();
     }
-    return current_pop;
+    sim.pop
+}
+
+// a / b for counts of any size, from their leading digits so it doesn't overflow an f64
+fn ratio<C: Exact>(a: &C, b: &C) -> f64 {
+    let leading = |c: &C| {
+        let digits = c.to_string();
+        let used = digits.len().min(17);
+        (digits[..used].parse::<f64>().unwrap(), (digits.len() - used) as i32)
+    };
+    let ((a, a_exp), (b, b_exp)) = (leading(a), leading(b));
+    a / b * 10f64.powi(a_exp - b_exp)
+}
+
+// One line per day: the day, the total, the fish per timer and how much the total grew
+// since the day before.
+pub fn to_csv<C: Exact>(days: impl IntoIterator<Item = Day<C>>) -> String {
+    let mut csv = String::new();
+    let mut previous: Option<C> = None;
+    for day in days {
+        if previous.is_none() {
+            let timers: Vec<String> = (0..day.timers.len()).map(|t| format!("t{}", t)).collect();
+            csv += &format!("day,total,{},growth\n", timers.join(","));
+        }
+        let timers: Vec<String> = day.timers.iter().map(|c| c.to_string()).collect();
+        let growth = match &previous {
+            Some(p) if p.to_string() != "0" => format!("{}", ratio(&day.total, p)),
+            _ => String::new(),
+        };
+        csv += &format!("{},{},{},{}\n", day.day, day.total, timers.join(","), growth);
+        previous = Some(day.total);
+    }
+    csv
+}
+
+// The dominant eigenvalue of the transition, which is what the population ends up
+// growing by every day. The fish spawning on a day are the ones that spawned reset + 1
+// days earlier plus the newborns of newborn + 1 days earlier, so it's the positive root
+// of 1 = x^-(reset + 1) + litter * x^-(newborn + 1). That side only goes down as x goes
+// up and is at least 1 in x = 1, so bisection finds it. When fish die there's no such
+// matrix, and no eigenvalue.
+pub fn growth_rate(lc: &LifeCycle) -> Option<f64> {
+    if !lc.is_linear() {
+        return None;
+    }
+    let f = |x: f64| x.powi(-(lc.reset as i32 + 1)) + lc.litter as f64 * x.powi(-(lc.newborn as i32 + 1));
+    let mut lo = 1.0;
+    let mut hi = 2.0 + lc.litter as f64;
+    for _ in 0..100 {
+        let mid = (lo + hi) / 2.0;
+        if f(mid) > 1.0 {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    Some(lo)
+}
+
+// new[i] = sum of m[i][j] * old[j]
+type Matrix<C> = Vec<Vec<C>>;
+
+// What one step does to the number of fish per timer.
+fn transition<C: Count>(lc: &LifeCycle, timers: usize, zero: &C) -> Matrix<C> {
+    let mut m = vec![vec![zero.clone(); timers]; timers];
+    for timer in 1..timers {
+        m[timer - 1][timer] = zero.constant(1);
+    }
+    m[lc.reset as usize][0] = zero.constant(1);
+    m[lc.newborn as usize][0] = m[lc.newborn as usize][0].add(&zero.constant(lc.litter));
+    m
+}
+
+fn identity<C: Count>(timers: usize, zero: &C) -> Matrix<C> {
+    (0..timers).map(|i| (0..timers).map(|j| zero.constant(if i == j { 1 } else { 0 })).collect())
+               .collect()
+}
+
+fn mat_mul<C: Count>(a: &Matrix<C>, b: &Matrix<C>) -> Matrix<C> {
+    let n = a.len();
+    (0..n).map(|i| (0..n).map(|j| (0..n).fold(a[i][0].constant(0), |acc, k| acc.add(&a[i][k].mul(&b[k][j]))))
+                         .collect())
+          .collect()
+}
+
+fn mat_pow<C: Count>(mut power: Matrix<C>, mut days: u64) -> Matrix<C> {
+    let mut result: Matrix<C> = identity(power.len(), &power[0][0]);
+    while days > 0 {
+        if days & 1 == 1 {
+            result = mat_mul(&result, &power);
+        }
+        days >>= 1;
+        // squaring once more than needed could overflow for no reason
+        if days > 0 {
+            power = mat_mul(&power, &power);
+        }
+    }
+    result
+}
+
+// Same as simulate, but squares the transition matrix instead of stepping every day,
+// so it only takes O(log days) matrix multiplications. When fish die (of old age or
+// otherwise), this just simulates every day.
+pub fn fast_forward<C: Count, const N: usize>(pop: &Population<C, N>, lc: &LifeCycle, days: u64) -> Population<C, N> {
+    if !lc.is_linear() {
+        let steps = days.try_into().expect("Too many days to simulate with fish dying");
+        return simulate(pop.clone(), lc, steps);
+    }
+    assert!(lc.timers() <= N, "Life cycle needs more than {} timers", N);
+
+    // only as big as needed, N can be a lot more than the life cycle uses
+    let zero = pop.get(0).constant(0);
+    let timers = (0..N).filter(|t| *pop.get(*t) != zero)
+                       .map(|t| t + 1)
+                       .fold(lc.timers(), usize::max);
+    let result: Matrix<C> = mat_pow(transition(lc, timers, &zero), days);
+
+    let mut next = Population {
+        counts: std::array::from_fn(|_| zero.clone()),
+        head: 0,
+    };
+    for (timer, row) in result.iter().enumerate() {
+        next.counts[timer] = (0..timers).fold(zero.clone(), |acc, t| acc.add(&row[t].mul(pop.get(t))));
+    }
+    next
+}
+
+pub struct Species<C: Count = FishCount, const N: usize = TIMERS> {
+    pub lc: LifeCycle,
+    pub pop: Population<C, N>,
+}
+
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub enum Interaction {
+    // every fish of parent that spawns also spawns litter newborns of child
+    Spawns { parent: usize, child: usize, litter: u64 },
+    // count fish of species arrive every day, with the given timer
+    Immigration { species: usize, timer: Lifetime, count: u64 },
+    // every fish of predator stops per_fish newborns of prey from being born
+    LimitsSpawning { predator: usize, prey: usize, per_fish: u64 },
+}
+
+impl Interaction {
+    // whether the day still is new = matrix * old + constant
+    fn is_affine(&self) -> bool {
+        !matches!(self, Interaction::LimitsSpawning { .. })
+    }
+}
+
+// "spawns:parent:child:litter", "immigration:species:timer:count" or "limits:predator:prey:per_fish"
+impl FromStr for Interaction {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.trim().split(':').collect();
+        let [kind, a, b, c] = parts.as_slice() else { return Err(()) };
+        let a: usize = a.parse().map_err(|_| ())?;
+        let c: u64 = c.parse().map_err(|_| ())?;
+        match *kind {
+            "spawns" => Ok(Interaction::Spawns { parent: a, child: b.parse().map_err(|_| ())?, litter: c }),
+            "immigration" => Ok(Interaction::Immigration { species: a, timer: b.parse().map_err(|_| ())?, count: c }),
+            "limits" => Ok(Interaction::LimitsSpawning { predator: a, prey: b.parse().map_err(|_| ())?, per_fish: c }),
+            _ => Err(()),
+        }
+    }
+}
+
+// (timer, how many) of the fish of one species that arrived on the same day
+type Arrivals<C> = Vec<(Lifetime, C)>;
+
+pub struct Ecosystem<C: Count = FishCount, const N: usize = TIMERS> {
+    species: Vec<Species<C, N>>,
+    interactions: Vec<Interaction>,
+}
+
+impl<C: Count, const N: usize> Ecosystem<C, N> {
+    // None without any species, or if an interaction is about a species that isn't there
+    // or a timer that doesn't fit
+    pub fn new(species: Vec<Species<C, N>>, interactions: Vec<Interaction>) -> Option<Self> {
+        let exists = |i: usize| i < species.len();
+        let valid = interactions.iter().all(|i| match *i {
+            Interaction::Spawns { parent, child, .. } => exists(parent) && exists(child),
+            Interaction::Immigration { species, timer, .. } => exists(species) && (timer as usize) < N,
+            Interaction::LimitsSpawning { predator, prey, .. } => exists(predator) && exists(prey),
+        });
+        (valid && !species.is_empty()).then_some(Ecosystem { species, interactions })
+    }
+
+    fn zero(&self) -> C {
+        self.species[0].pop.get(0).constant(0)
+    }
+
+    pub fn num_fish(&self) -> Vec<C> {
+        self.species.iter().map(|s| s.pop.num_fish()).collect()
+    }
+
+    fn is_affine(&self) -> bool {
+        self.species.iter().all(|s| s.lc.is_linear()) && self.interactions.iter().all(|i| i.is_affine())
+    }
+
+    pub fn simulate(self, days: u64) -> Self {
+        if self.is_affine() {
+            self.fast_forward(days)
+        } else {
+            self.step_every_day(days.try_into().expect("Too many days to simulate day by day"))
+        }
+    }
+
+    // One block of timers per species, and one more entry that is always 1 for the constants.
+    fn fast_forward(mut self, days: u64) -> Self {
+        let zero = self.zero();
+        let mut timers = 0;
+        for s in self.species.iter() {
+            assert!(s.lc.timers() <= N, "Life cycle needs more than {} timers", N);
+            timers = (0..N).filter(|t| *s.pop.get(*t) != zero).map(|t| t + 1).fold(timers.max(s.lc.timers()), usize::max);
+        }
+        for i in self.interactions.iter() {
+            if let Interaction::Immigration { timer, .. } = i {
+                timers = timers.max(*timer as usize + 1);
+            }
+        }
+        let one = self.species.len() * timers;
+
+        let mut m = vec![vec![zero.clone(); one + 1]; one + 1];
+        for (i, s) in self.species.iter().enumerate() {
+            let block: Matrix<C> = transition(&s.lc, timers, &zero);
+            for (row, values) in block.iter().enumerate() {
+                m[i * timers + row][i * timers..(i + 1) * timers].clone_from_slice(values);
+            }
+        }
+        for i in self.interactions.iter() {
+            let (row, column, n) = match *i {
+                Interaction::Spawns { parent, child, litter } => {
+                    (child * timers + self.species[child].lc.newborn as usize, parent * timers, litter)
+                }
+                Interaction::Immigration { species, timer, count } => (species * timers + timer as usize, one, count),
+                Interaction::LimitsSpawning { .. } => unreachable!(),
+            };
+            m[row][column] = m[row][column].add(&zero.constant(n));
+        }
+        m[one][one] = zero.constant(1);
+
+        let result = mat_pow(m, days);
+        let old: Vec<C> = self.species.iter()
+                              .flat_map(|s| (0..timers).map(|t| s.pop.get(t).clone()))
+                              .chain(std::iter::once(zero.constant(1)))
+                              .collect();
+        for (i, s) in self.species.iter_mut().enumerate() {
+            for t in 0..timers {
+                let row = &result[i * timers + t];
+                *s.pop.get_mut(t) = (0..=one).fold(zero.clone(), |acc, j| acc.add(&row[j].mul(&old[j])));
+            }
+        }
+        self
+    }
+
+    fn step_every_day(mut self, days: u32) -> Self {
+        // Like in Simulation, but with fish arriving from elsewhere too: per species, the
+        // day they arrived (the input on day 0), their timer and how many, while they live.
+        let zero = self.zero();
+        let mut arrivals: Vec<VecDeque<(u32, Arrivals<C>)>> = self.species.iter().map(|s| {
+            let initial = (0..N).filter(|t| *s.pop.get(*t) != zero).map(|t| (t as Lifetime, s.pop.get(t).clone())).collect();
+            VecDeque::from([(0, initial)])
+        }).collect();
+
+        for day in 1..=days {
+            let spawning: Vec<C> = self.species.iter().map(|s| s.pop.get(0).clone()).collect();
+            let mut newborns: Vec<C> = self.species.iter().zip(spawning.iter())
+                                           .map(|(s, n)| n.mul(&zero.constant(s.lc.litter)))
+                                           .collect();
+            let mut arrived: Vec<Arrivals<C>> = vec![Vec::new(); self.species.len()];
+            for i in self.interactions.iter() {
+                match *i {
+                    Interaction::Spawns { parent, child, litter } => {
+                        newborns[child] = newborns[child].add(&spawning[parent].mul(&zero.constant(litter)));
+                    }
+                    Interaction::Immigration { species, timer, count } => arrived[species].push((timer, zero.constant(count))),
+                    Interaction::LimitsSpawning { .. } => (),
+                }
+            }
+            // limits go last, they can stop newborns from any parent
+            for i in self.interactions.iter() {
+                if let Interaction::LimitsSpawning { predator, prey, per_fish } = *i {
+                    let stopped = self.species[predator].pop.num_fish().mul(&zero.constant(per_fish));
+                    newborns[prey] = newborns[prey].sub_or_zero(&stopped)
+                                                   .expect("Can't tell how many newborns are stopped when counting modulo a number");
+                }
+            }
+
+            for (i, s) in self.species.iter_mut().enumerate() {
+                s.pop.rotate(&LifeCycle { litter: 0, ..s.lc.clone() });
+                arrived[i].push((s.lc.newborn, newborns[i].clone()));
+                for (timer, count) in arrived[i].iter() {
+                    s.pop.add(*timer, count);
+                }
+
+                arrivals[i].push_back((day, std::mem::take(&mut arrived[i])));
+                if let Some(max_age) = s.lc.max_age {
+                    while let Some((arrived_on, _)) = arrivals[i].front() {
+                        let age = day - arrived_on;
+                        if age < max_age {
+                            break;
+                        }
+                        for (timer, count) in arrivals[i].pop_front().unwrap().1 {
+                            s.pop.remove(s.lc.timer_after(timer, age), &count);
+                        }
+                    }
+                } else {
+                    arrivals[i].clear();
+                }
+
+                if let Some(percent) = s.lc.mortality {
+                    if s.lc.max_age.is_none() {
+                        s.pop.cull(percent);
+                    }
+                    for (arrived_on, group) in arrivals[i].iter_mut() {
+                        for (timer, count) in group.iter_mut() {
+                            let died = dying(count, percent);
+                            *count = count.sub(&died);
+                            s.pop.remove(s.lc.timer_after(*timer, day - *arrived_on), &died);
+                        }
+                    }
+                }
+            }
+        }
+        self
+    }
+}
+
+// One species per line: its timers, optionally followed by reset=, newborn=, litter=,
+// max-age= and mortality= to change its life cycle from the given one.
+fn parse_species<C: Count, const N: usize>(line: &str, lc: &LifeCycle, zero: C) -> Result<Species<C, N>, ()> {
+    let mut parts = line.split_whitespace();
+    let pop = Population::parse_with(parts.next().ok_or(())?, zero)?;
+    let mut lc = lc.clone();
+    for setting in parts {
+        let (key, value) = setting.split_once('=').ok_or(())?;
+        match key {
+            "reset" => lc.reset = value.parse().map_err(|_| ())?,
+            "newborn" => lc.newborn = value.parse().map_err(|_| ())?,
+            "litter" => lc.litter = value.parse().map_err(|_| ())?,
+            "max-age" => lc.max_age = Some(value.parse().map_err(|_| ())?),
+            "mortality" => lc.mortality = Some(value.parse().ok().filter(|p| *p <= 100).ok_or(())?),
+            _ => return Err(()),
+        }
+    }
+    Ok(Species { lc, pop })
+}
+
+// The number of fish of every species after the given days, one per line.
+fn print_ecosystem<C: Count>(lines: &[&str], lc: &LifeCycle, interactions: Vec<Interaction>, days: u64, zero: C) {
+    let species = lines.iter()
+                       .map(|l| parse_species::<C, MAX_TIMERS>(l, lc, zero.clone()).expect("Failed to parse"))
+                       .collect();
+    let ecosystem = Ecosystem::new(species, interactions).expect("Interaction with a species or timer that isn't there");
+    for count in ecosystem.simulate(days).num_fish() {
+        println!("{}", count);
+    }
+}
+
+fn number_arg<T: FromStr>(args: &mut impl Iterator<Item = String>, name: &str) -> T {
+    args.next()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or_else(|| panic!("{} needs a number", name))
 }
 
 fn main() {
+    let mut days = 256;
+    let mut exact = false;
+    let mut modulo = None;
+    let mut lc = LifeCycle::default();
+    let mut csv = None;
+    let mut interactions = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--days" => days = number_arg(&mut args, "--days"),
+            // count with arbitrary precision instead of u128
+            "--exact" => exact = true,
+            // count modulo a (positive) number instead
+            "--modulo" => {
+                modulo = Some(number_arg(&mut args, "--modulo"));
+                assert!(modulo != Some(0), "--modulo needs a positive number");
+            },
+            "--reset" => lc.reset = number_arg(&mut args, "--reset"),
+            "--newborn" => lc.newborn = number_arg(&mut args, "--newborn"),
+            "--litter" => lc.litter = number_arg(&mut args, "--litter"),
+            "--max-age" => lc.max_age = Some(number_arg(&mut args, "--max-age")),
+            // percent of the fish that die every day
+            "--mortality" => {
+                lc.mortality = Some(number_arg(&mut args, "--mortality"));
+                assert!(lc.mortality <= Some(100), "--mortality needs a percentage");
+            },
+            // write the population of every day to a file
+            "--csv" => csv = Some(args.next().expect("--csv needs a file name")),
+            // between the species on the lines of the input, e.g. limits:1:0:2
+            "--interaction" => interactions.push(args.next()
+                                                     .and_then(|i| i.parse().ok())
+                                                     .expect("--interaction needs an interaction")),
+            _ => panic!("Unknown argument {}", arg),
+        }
+    }
+
     let mut input = String::new();
 
     io::stdin()
         .read_to_string(&mut input)
         .expect("Failed to read input");
 
-    let result = simulate(&input.parse().expect("Failed to parse"), 256);
-    println!("{}", result.num_fish());
+    assert!(!exact || modulo.is_none(), "Count either exactly or modulo a number, not both");
+
+    let lines: Vec<&str> = input.lines().filter(|l| !l.trim().is_empty()).collect();
+    if lines.len() > 1 || !interactions.is_empty() {
+        assert!(csv.is_none(), "--csv only works with a single species");
+        match modulo {
+            Some(m) => print_ecosystem(&lines, &lc, interactions, days, Modular::zero(m)),
+            None if exact => print_ecosystem(&lines, &lc, interactions, days, BigCount::from(0)),
+            None => print_ecosystem(&lines, &lc, interactions, days, 0 as FishCount),
+        }
+        return;
+    }
+
+    if let Some(file) = csv {
+        let pop: Population<BigCount, MAX_TIMERS> = input.parse().expect("Failed to parse");
+        let days = days.try_into().expect("Too many days for a CSV");
+        let series: Vec<Day<BigCount>> = Simulation::new(pop, &lc).take(days).collect();
+        // the growth wobbles from day to day, so average it over the last (up to) 100 days
+        let span = series.len().saturating_sub(1).min(100);
+        // and there is nothing to grow from once everyone died
+        if span > 0 && series[series.len() - 1 - span].total.to_string() != "0" {
+            let (first, last) = (&series[series.len() - 1 - span], &series[series.len() - 1]);
+            let growth = ratio(&last.total, &first.total).powf(1.0 / span as f64);
+            match growth_rate(&lc) {
+                Some(rate) => println!("Growth over the last {} days: {:.6} per day, dominant eigenvalue: {:.6}",
+                                       span, growth, rate),
+                None => println!("Growth over the last {} days: {:.6} per day (fish die, so there's no eigenvalue to compare with)",
+                                 span, growth),
+            }
+        }
+        fs::write(&file, to_csv(series)).expect("Failed to write CSV");
+    }
+
+    if let Some(m) = modulo {
+        let pop: Population<Modular, MAX_TIMERS> = Population::parse_with(&input, Modular::zero(m)).expect("Failed to parse");
+        println!("{}", fast_forward(&pop, &lc, days).num_fish());
+    } else if exact {
+        let pop: Population<BigCount, MAX_TIMERS> = input.parse().expect("Failed to parse");
+        println!("{}", fast_forward(&pop, &lc, days).num_fish());
+    } else {
+        let pop: Population<FishCount, MAX_TIMERS> = input.parse().expect("Failed to parse");
+        println!("{}", fast_forward(&pop, &lc, days).num_fish());
+    }
 }
 
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
 
     #[test]
     fn test_step() {
-        let pop = "3,4,3,1,2".parse().unwrap();
-        assert_eq!(step(&pop), "2,3,2,0,1".parse().unwrap());
+        let pop: Population = "3,4,3,1,2".parse().unwrap();
+        assert_eq!(step(&pop, &LifeCycle::default()), "2,3,2,0,1".parse().unwrap());
+        let old = LifeCycle { max_age: Some(1), ..LifeCycle::default() };
+        assert_eq!(step(&pop, &old).num_fish(), 0);
     }
 
     #[test]
     fn test_simulate() {
-        let pop = "3,4,3,1,2".parse().unwrap();
-        assert_eq!(simulate(&pop, 18), "6,0,6,4,5,6,0,1,1,2,6,0,1,1,1,2,2,3,3,4,6,7,8,8,8,8".parse().unwrap());
-        assert_eq!(simulate(&pop, 256).num_fish(), 26984457539);
+        let lc = LifeCycle::default();
+        let pop: Population = "3,4,3,1,2".parse().unwrap();
+        assert_eq!(simulate(pop.clone(), &lc, 18), "6,0,6,4,5,6,0,1,1,2,6,0,1,1,1,2,2,3,3,4,6,7,8,8,8,8".parse().unwrap());
+        assert_eq!(simulate(pop, &lc, 256).num_fish(), 26984457539);
+    }
+
+    #[test]
+    fn test_fast_forward() {
+        let lc = LifeCycle::default();
+        let pop: Population = "3,4,3,1,2".parse().unwrap();
+        for days in 0..100 {
+            assert_eq!(fast_forward(&pop, &lc, days), simulate(pop.clone(), &lc, days as u32));
+        }
+        assert_eq!(fast_forward(&pop, &lc, 256).num_fish(), 26984457539);
+        assert_eq!(fast_forward(&pop, &lc, 1000), simulate(pop.clone(), &lc, 1000));
     }
-    
 
-}
\ No newline at end of file
+    #[test]
+    fn test_big_count() {
+        let big = |n: u64| BigCount::from(n);
+        assert_eq!(big(0).to_string(), "0");
+        assert_eq!(big(1_000_000_000).to_string(), "1000000000");
+        assert_eq!(big(u64::MAX).add(&big(1)).to_string(), "18446744073709551616");
+        let mut power = big(1);
+        for _ in 0..100 {
+            power = power.mul(&big(2));
+        }
+        assert_eq!(power.to_string(), "1267650600228229401496703205376");
+        assert_eq!(power.mul(&big(0)), big(0));
+        assert_eq!(big(123456789).mul(&big(987654321)).to_string(), "121932631112635269");
+        assert_eq!(power.percent(30).unwrap().to_string(), "380295180068468820449010961612");
+        assert_eq!(big(99).percent(50), Some(big(49)));
+    }
+
+    #[test]
+    fn test_long_horizons() {
+        let lc = LifeCycle::default();
+        let pop: Population<BigCount> = "3,4,3,1,2".parse().unwrap();
+        assert_eq!(fast_forward(&pop, &lc, 256).num_fish().to_string(), "26984457539");
+        assert_eq!(simulate(pop.clone(), &lc, 80), fast_forward(&pop, &lc, 80));
+
+        let exact: FishCount = fast_forward::<FishCount, TIMERS>(&"3,4,3,1,2".parse().unwrap(), &lc, 900).num_fish();
+        assert_eq!(fast_forward(&pop, &lc, 900).num_fish().to_string(), exact.to_string());
+
+        // way past what fits in a u128, checked against counting modulo a prime
+        let big = fast_forward(&pop, &lc, 5000).num_fish();
+        assert!(big.to_string().len() > 100);
+        for m in [1_000_000_007, 7, 1] {
+            let modular: Population<Modular> = Population::parse_with("3,4,3,1,2", Modular::zero(m)).unwrap();
+            let expected = fast_forward(&modular, &lc, 5000).num_fish();
+            let mut reduced = 0;
+            for digit in big.to_string().chars() {
+                reduced = (reduced * 10 + digit.to_digit(10).unwrap() as u64) % m;
+            }
+            assert_eq!(expected, Modular { value: reduced, modulus: m });
+            assert_eq!(simulate(modular.clone(), &lc, 5000).num_fish(), expected);
+        }
+    }
+
+    // how many fish there are per (timer, age), with the age always 0 without a maximum age
+    fn brute_force(timers: &[Lifetime], lc: &LifeCycle, steps: u32) -> u64 {
+        let mut fish: HashMap<(Lifetime, u32), u64> = HashMap::new();
+        for timer in timers {
+            *fish.entry((*timer, 0)).or_insert(0) += 1;
+        }
+        let older = if lc.max_age.is_some() { 1 } else { 0 };
+        for _ in 0..steps {
+            let mut next = HashMap::new();
+            let mut born = 0;
+            for ((timer, age), count) in fish {
+                let timer = if timer == 0 {
+                    born += count * lc.litter;
+                    lc.reset
+                } else {
+                    timer - 1
+                };
+                *next.entry((timer, age + older)).or_insert(0) += count;
+            }
+            *next.entry((lc.newborn, 0)).or_insert(0) += born;
+            next.retain(|(_, age), _| lc.max_age.is_none_or(|max| *age < max));
+            if let Some(percent) = lc.mortality {
+                for count in next.values_mut() {
+                    *count -= *count * percent as u64 / 100;
+                }
+            }
+            fish = next;
+        }
+        fish.values().sum()
+    }
+
+    #[test]
+    fn test_life_cycles() {
+        let lc = LifeCycle::default();
+        assert_eq!(lc.timer_after(3, 3), 0);
+        assert_eq!(lc.timer_after(3, 4), 6);
+        assert_eq!(lc.timer_after(3, 10), 0);
+        assert_eq!(lc.timer_after(3, 11), 6);
+        assert_eq!(lc.timer_after(8, 16), 6);
+
+        let cycles = [
+            LifeCycle { reset: 4, newborn: 6, litter: 1, max_age: None, mortality: None },
+            LifeCycle { reset: 6, newborn: 8, litter: 3, max_age: None, mortality: None },
+            LifeCycle { reset: 9, newborn: 2, litter: 2, max_age: None, mortality: None },
+            LifeCycle { reset: 6, newborn: 8, litter: 1, max_age: Some(20), mortality: None },
+            LifeCycle { reset: 3, newborn: 5, litter: 2, max_age: Some(7), mortality: None },
+            LifeCycle { reset: 2, newborn: 2, litter: 0, max_age: Some(4), mortality: None },
+            LifeCycle { reset: 6, newborn: 8, litter: 1, max_age: Some(1), mortality: None },
+            LifeCycle { reset: 6, newborn: 8, litter: 1, max_age: Some(0), mortality: None },
+            LifeCycle { reset: 6, newborn: 8, litter: 1, max_age: None, mortality: Some(5) },
+            LifeCycle { reset: 3, newborn: 5, litter: 3, max_age: None, mortality: Some(30) },
+            LifeCycle { reset: 6, newborn: 8, litter: 2, max_age: Some(12), mortality: Some(10) },
+            LifeCycle { reset: 6, newborn: 8, litter: 1, max_age: None, mortality: Some(100) },
+            LifeCycle { reset: 6, newborn: 8, litter: 1, max_age: Some(3), mortality: Some(0) },
+        ];
+        let timers = [3, 4, 3, 1, 2];
+        let pop: Population<FishCount, 16> = "3,4,3,1,2".parse().unwrap();
+        for lc in &cycles {
+            for steps in 0..45 {
+                let expected = brute_force(&timers, lc, steps) as FishCount;
+                assert_eq!(simulate(pop.clone(), lc, steps).num_fish(), expected, "{:?} {}", lc, steps);
+                assert_eq!(fast_forward(&pop, lc, steps as u64).num_fish(), expected, "{:?} {}", lc, steps);
+                let alone = Ecosystem { species: vec![Species { lc: lc.clone(), pop: pop.clone() }], interactions: vec![] };
+                assert_eq!(alone.step_every_day(steps).num_fish(), vec![expected], "{:?} {}", lc, steps);
+            }
+        }
+    }
+
+    #[test]
+    fn test_time_series() {
+        let lc = LifeCycle::default();
+        let pop: Population = "3,4,3,1,2".parse().unwrap();
+        let series: Vec<Day> = Simulation::new(pop.clone(), &lc).take(80).collect();
+        assert_eq!(series[0], Day { day: 1, total: 5, timers: vec![1, 1, 2, 1, 0, 0, 0, 0, 0] });
+        assert_eq!(series[17].total, 26);
+        assert_eq!(series[79].total, 5934);
+        for day in &series {
+            assert_eq!(day.total, simulate(pop.clone(), &lc, day.day).num_fish());
+            assert_eq!(day.total, day.timers.iter().sum());
+        }
+
+        let csv = to_csv(series.into_iter().take(3));
+        assert_eq!(csv, "day,total,t0,t1,t2,t3,t4,t5,t6,t7,t8,growth\n\
+                         1,5,1,1,2,1,0,0,0,0,0,\n\
+                         2,6,1,2,1,0,0,0,1,0,1,1.2\n\
+                         3,7,2,1,0,0,0,1,1,1,1,1.1666666666666667\n");
+
+        // after a while the population grows by about the dominant eigenvalue every day
+        // (the next eigenvalues are not that much smaller, so it takes a while)
+        let rate = growth_rate(&lc).unwrap();
+        assert!((rate - 1.0910).abs() < 1e-4, "{}", rate);
+        let late: Vec<Day> = Simulation::new(pop, &lc).skip(399).take(101).collect();
+        let average = (late[100].total as f64 / late[0].total as f64).powf(0.01);
+        assert!((average - rate).abs() < 1e-4, "{} {}", average, rate);
+        assert_eq!(growth_rate(&LifeCycle { litter: 0, ..LifeCycle::default() }), Some(1.0));
+        assert_eq!(growth_rate(&LifeCycle { max_age: Some(10), ..LifeCycle::default() }), None);
+        assert_eq!(growth_rate(&LifeCycle { mortality: Some(1), ..LifeCycle::default() }), None);
+
+        // way past what an f64 holds, the growth is still there
+        let pop: Population<BigCount> = "3,4,3,1,2".parse().unwrap();
+        let huge: Vec<Day<BigCount>> = Simulation::new(pop, &lc).skip(9000).take(2).collect();
+        assert!(huge[0].total.to_string().len() > 330);
+        assert!((ratio(&huge[1].total, &huge[0].total) - rate).abs() < 1e-3);
+        let csv = to_csv(huge);
+        let growth: f64 = csv.lines().last().unwrap().rsplit(',').next().unwrap().parse().unwrap();
+        assert!((growth - rate).abs() < 1e-3, "{}", growth);
+    }
+
+    #[test]
+    fn test_ecosystem() {
+        let lc = LifeCycle::default();
+        let other = LifeCycle { reset: 4, newborn: 6, litter: 2, max_age: None, mortality: None };
+        let ecosystem = |interactions: Vec<Interaction>| Ecosystem::<FishCount, TIMERS>::new(
+            vec![
+                Species { lc: lc.clone(), pop: "3,4,3,1,2".parse().unwrap() },
+                Species { lc: other.clone(), pop: "1,1".parse().unwrap() },
+            ],
+            interactions,
+        ).unwrap();
+
+        // without interactions every species is on its own
+        let pop: Population = "1,1".parse().unwrap();
+        assert_eq!(ecosystem(vec![]).simulate(80).num_fish(), vec![5934, fast_forward(&pop, &other, 80).num_fish()]);
+
+        // the matrix does the same as going day by day
+        let interactions: Vec<Interaction> = ["spawns:0:1:1", "immigration:0:3:2", "spawns:1:0:3"]
+            .iter()
+            .map(|i| i.parse().unwrap())
+            .collect();
+        assert!(ecosystem(interactions.clone()).is_affine());
+        for days in 0..60 {
+            assert_eq!(ecosystem(interactions.clone()).fast_forward(days).num_fish(),
+                       ecosystem(interactions.clone()).step_every_day(days as u32).num_fish());
+        }
+
+        // enough predators and the prey never has newborns
+        let limited = ecosystem(vec![Interaction::LimitsSpawning { predator: 1, prey: 0, per_fish: 5 }]);
+        assert!(!limited.is_affine());
+        assert_eq!(limited.simulate(80).num_fish()[0], 5);
+        let unlimited = ecosystem(vec![Interaction::LimitsSpawning { predator: 1, prey: 0, per_fish: 0 }]);
+        assert_eq!(unlimited.simulate(80).num_fish()[0], 5934);
+
+        // every species in an interaction has to be there
+        let species = || vec![Species::<FishCount, TIMERS> { lc: lc.clone(), pop: "1".parse().unwrap() }];
+        assert!(Ecosystem::new(species(), vec![Interaction::Spawns { parent: 0, child: 1, litter: 1 }]).is_none());
+        assert!(Ecosystem::new(species(), vec![Interaction::LimitsSpawning { predator: 5, prey: 0, per_fish: 1 }]).is_none());
+        assert!(Ecosystem::new(species(), vec![Interaction::Immigration { species: 0, timer: 9, count: 1 }]).is_none());
+        assert!(Ecosystem::new(species(), vec![Interaction::Immigration { species: 0, timer: 8, count: 1 }]).is_some());
+
+        // immigrants grow old and die too
+        let visitors = Ecosystem::<FishCount, TIMERS> {
+            species: vec![Species { lc: LifeCycle { litter: 0, max_age: Some(3), ..lc.clone() }, pop: "5".parse().unwrap() }],
+            interactions: vec![Interaction::Immigration { species: 0, timer: 0, count: 1 }],
+        };
+        assert_eq!(visitors.simulate(10).num_fish(), vec![3]);
+        assert!(Ecosystem::<FishCount, TIMERS>::new(vec![], vec![]).is_none());
+
+        // other counts work the same, also past what fits in a u128
+        let big = |interactions: Vec<Interaction>| Ecosystem::<BigCount, TIMERS>::new(
+            vec![
+                Species { lc: lc.clone(), pop: "3,4,3,1,2".parse().unwrap() },
+                Species { lc: other.clone(), pop: "1,1".parse().unwrap() },
+            ],
+            interactions,
+        ).unwrap();
+        let limits = vec![Interaction::LimitsSpawning { predator: 1, prey: 0, per_fish: 5 }];
+        let expected: Vec<String> = ecosystem(limits.clone()).simulate(80).num_fish().iter().map(|c| c.to_string()).collect();
+        assert_eq!(big(limits).simulate(80).num_fish().iter().map(|c| c.to_string()).collect::<Vec<_>>(), expected);
+
+        let huge = big(interactions.clone()).simulate(3000).num_fish();
+        assert!(huge[0].to_string().len() > 100);
+        let m = 1_000_000_007;
+        let modular = Ecosystem::<Modular, TIMERS>::new(
+            vec![
+                Species { lc: lc.clone(), pop: Population::parse_with("3,4,3,1,2", Modular::zero(m)).unwrap() },
+                Species { lc: other.clone(), pop: Population::parse_with("1,1", Modular::zero(m)).unwrap() },
+            ],
+            interactions,
+        ).unwrap();
+        let expected: Vec<u64> = huge.iter().map(|c| c.div_rem(m).1).collect();
+        assert_eq!(modular.simulate(3000).num_fish().iter().map(|c| c.value).collect::<Vec<_>>(), expected);
+    }
+}
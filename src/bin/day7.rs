@@ -1,19 +1,21 @@
 use std::io;
 use std::io::Read;
+use std::ops::RangeInclusive;
+use std::env;
+use std::fs;
+use std::str::FromStr;
 
-pub fn min_distance1(nums: Vec<i64>) -> i64 {
-    // the median minimizes the sum of euclidian distances
-    let median = median(&nums);
+// The least fuel with the linear cost, and every position where it takes that much.
+pub fn min_distance1(nums: Vec<i64>) -> (i64, RangeInclusive<i64>) {
+    // the median minimizes the sum of euclidian distances,
+    // and with an even number of crabs so does everything between the two middle ones
+    let best = median(&nums, MedianPolicy::Interval);
 
-    return nums.iter().fold(0, |acc, x| acc + fuel_cost_linear(median, *x));
+    (nums.iter().fold(0, |acc, x| acc + fuel_cost_linear(*best.start(), *x)), best)
 }
 
 pub fn min_distance2(nums: Vec<i64>) -> i64 {
-    // I feel like it should be possible to find a closed form solution,
-    // but my maths are rusty, so we'll do it with very naive searching
-    let mean = minimize(&nums, fuel_cost_nonlinear);
-
-    return nums.iter().fold(0, |acc, x| acc + fuel_cost_nonlinear(mean, *x));
+    Crabs::new(&nums).best_nonlinear().1
 }
 
 fn fuel_cost_linear(from: i64, to: i64) -> i64 {
@@ -25,52 +27,502 @@ fn fuel_cost_nonlinear(from: i64, to: i64) -> i64 {
 }
 
 
-fn median(nums: &Vec<i64>) -> i64 {
-    let mut nums = nums.clone();
+// Which median to take when there's an even number of them.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum MedianPolicy {
+    Lower,
+    Upper,
+    // everything from the lower to the upper median
+    Interval,
+}
+
+fn median(nums: &[i64], policy: MedianPolicy) -> RangeInclusive<i64> {
+    let mut nums = nums.to_vec();
     nums.sort();
     let n = nums.len();
-    if n % 2 == 0 {
-        // this isn't mathematically correct but should be good enough,
-        // since we assume the solution can't have non-integer positions
-        return nums[n/2];
-    } else {
-        return nums[(n/2)+1];
-    }
-}
-
-// assuming convex function but no derivative available (since I'm lazy)
-fn minimize(nums: &Vec<i64>, cost: fn(i64, i64) -> i64) -> i64 {
-    let eval = |pos| nums.iter().fold(0, |acc, x| acc + cost(pos, *x));
-    let mut best_pos = median(nums);
-    let mut best_guess = eval(best_pos);
-    loop {
-        //this does way too many calculations but we can improve if needed
-        // - take bigger steps (use gradient / binary search)
-        // - memoize already evalled positions
-        let left = eval(best_pos - 1);
-        let right = eval(best_pos + 1);
-        if left < best_guess {
-            best_guess = left;
-            best_pos = best_pos - 1;
-        } else if right < best_guess {
-            best_guess = right;
-            best_pos = best_pos + 1;
+    let (lower, upper) = (nums[(n - 1) / 2], nums[n / 2]);
+    match policy {
+        MedianPolicy::Lower => lower..=lower,
+        MedianPolicy::Upper => upper..=upper,
+        MedianPolicy::Interval => lower..=upper,
+    }
+}
+
+// Sorted positions with prefix sums, so the total cost of any position can be
+// found with a binary search instead of going over every crab.
+pub struct Crabs {
+    positions: Vec<i64>,
+    // prefix[i] is the sum of the first i positions
+    prefix: Vec<i64>,
+    sum_of_squares: i64,
+}
+
+impl Crabs {
+    pub fn new(positions: &[i64]) -> Crabs {
+        let mut positions = positions.to_vec();
+        positions.sort();
+        let mut prefix = vec![0];
+        for p in positions.iter() {
+            prefix.push(prefix.last().unwrap() + p);
+        }
+        let sum_of_squares = positions.iter().map(|p| p * p).sum();
+        Crabs { positions, prefix, sum_of_squares }
+    }
+
+    fn len(&self) -> i64 {
+        self.positions.len() as i64
+    }
+
+    fn sum(&self) -> i64 {
+        *self.prefix.last().unwrap()
+    }
+
+    // sum of |pos - x|
+    pub fn linear_cost(&self, pos: i64) -> i64 {
+        let left = self.positions.partition_point(|x| *x < pos);
+        let (left_sum, right_sum) = (self.prefix[left], self.sum() - self.prefix[left]);
+        let left = left as i64;
+        (pos * left - left_sum) + (right_sum - pos * (self.len() - left))
+    }
+
+    // sum of |pos - x| * (|pos - x| + 1) / 2, which is half of the squares plus the distances
+    pub fn nonlinear_cost(&self, pos: i64) -> i64 {
+        let squares = self.len() * pos * pos - 2 * pos * self.sum() + self.sum_of_squares;
+        (squares + self.linear_cost(pos)) / 2
+    }
+
+    // Setting the derivative of the nonlinear cost to 0 gives n * (pos - mean) = -1/2 * (the
+    // number of crabs right of pos - the number left of it), so the best real position is
+    // within 1/2 of the mean. The cost is convex, so the best integer is next to it.
+    pub fn best_nonlinear(&self) -> (i64, i64) {
+        let mean = self.sum().div_euclid(self.len());
+        (mean - 1..=mean + 2).map(|pos| (pos, self.nonlinear_cost(pos)))
+                             .min_by_key(|(_, cost)| *cost)
+                             .unwrap()
+    }
+}
+
+// How the total cost behaves as the position moves, which decides how to look for the best one.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum Convexity {
+    // cost is the distance, so the median is best
+    Linear,
+    // the best real position is less than a position away from the mean
+    NearMean,
+    // cost per crab is convex in the distance, so the total is convex in the position
+    Convex,
+    // anything can happen, try every position
+    Unknown,
+}
+
+// Fuel for crab number `crab` to move `distance`, which should never go down as the
+// distance goes up (so the best position is somewhere between the crabs).
+pub trait FuelCost {
+    fn fuel(&self, crab: usize, distance: i64) -> i64;
+
+    fn convexity(&self) -> Convexity {
+        Convexity::Unknown
+    }
+}
+
+pub struct Linear;
+
+impl FuelCost for Linear {
+    fn fuel(&self, _: usize, distance: i64) -> i64 {
+        fuel_cost_linear(0, distance)
+    }
+
+    fn convexity(&self) -> Convexity {
+        Convexity::Linear
+    }
+}
+
+pub struct Triangular;
+
+impl FuelCost for Triangular {
+    fn fuel(&self, _: usize, distance: i64) -> i64 {
+        fuel_cost_nonlinear(0, distance)
+    }
+
+    fn convexity(&self) -> Convexity {
+        Convexity::NearMean
+    }
+}
+
+pub struct Quadratic;
+
+impl FuelCost for Quadratic {
+    fn fuel(&self, _: usize, distance: i64) -> i64 {
+        distance * distance
+    }
+
+    // the best real position is the mean itself
+    fn convexity(&self) -> Convexity {
+        Convexity::NearMean
+    }
+}
+
+// base^distance - 1, but never more than cap. Once the cap is reached, moving further
+// is free, so the total isn't convex anymore.
+pub struct ExponentialCapped {
+    pub base: i64,
+    pub cap: i64,
+}
+
+impl FuelCost for ExponentialCapped {
+    fn fuel(&self, _: usize, distance: i64) -> i64 {
+        let mut fuel: i64 = 1;
+        for _ in 0..distance {
+            fuel = fuel.saturating_mul(self.base);
+            if fuel > self.cap {
+                return self.cap;
+            }
+        }
+        // a base of 0 would make it -1
+        (fuel - 1).clamp(0, self.cap.max(0))
+    }
+}
+
+// Every crab has its own weight to multiply the fuel with, one per crab. The weights
+// can't be negative, or the fuel would go down as the distance goes up.
+pub struct Weighted<F: FuelCost> {
+    pub weights: Vec<i64>,
+    pub cost: F,
+}
+
+impl<F: FuelCost> FuelCost for Weighted<F> {
+    fn fuel(&self, crab: usize, distance: i64) -> i64 {
+        self.weights[crab] * self.cost.fuel(crab, distance)
+    }
+
+    // weighted sums of convex functions are convex, but the median and mean no longer work
+    fn convexity(&self) -> Convexity {
+        match self.cost.convexity() {
+            _ if self.weights.iter().any(|w| *w < 0) => Convexity::Unknown,
+            Convexity::Unknown => Convexity::Unknown,
+            _ => Convexity::Convex,
+        }
+    }
+}
+
+// Fuel per distance from a table, going up by the last step after the end of it.
+// An empty table makes moving free.
+pub struct Table(pub Vec<i64>);
+
+impl Table {
+    // whether the fuel never goes down as the distance goes up
+    fn is_increasing(&self) -> bool {
+        self.0.windows(2).all(|w| w[0] <= w[1])
+    }
+}
+
+impl FuelCost for Table {
+    fn fuel(&self, _: usize, distance: i64) -> i64 {
+        let table = &self.0;
+        if table.is_empty() {
+            return 0;
+        }
+        let last = table.len() as i64 - 1;
+        if distance <= last {
+            return table[distance as usize];
+        }
+        let step = if last > 0 { table[last as usize] - table[last as usize - 1] } else { 0 };
+        table[last as usize] + (distance - last) * step
+    }
+
+    fn convexity(&self) -> Convexity {
+        // fuel going down would make the best position depend on more than the slope
+        if self.is_increasing() && self.0.windows(3).all(|w| w[1] - w[0] <= w[2] - w[1]) {
+            Convexity::Convex
         } else {
-            return best_pos;
+            Convexity::Unknown
+        }
+    }
+}
+
+pub fn total_fuel<F: FuelCost + ?Sized>(nums: &[i64], cost: &F, pos: i64) -> i64 {
+    nums.iter().enumerate().map(|(crab, x)| cost.fuel(crab, (pos - x).abs())).sum()
+}
+
+// Binary search on the slope of a convex function: the first position where going
+// right doesn't help anymore.
+fn slope_search(mut lo: i64, mut hi: i64, f: impl Fn(i64) -> i64) -> i64 {
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if f(mid + 1) < f(mid) {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    lo
+}
+
+// The best position and its total fuel, looking in the way the cost model allows.
+pub fn optimize<F: FuelCost + ?Sized>(nums: &[i64], cost: &F) -> (i64, i64) {
+    let eval = |pos| (pos, total_fuel(nums, cost, pos));
+    let cheapest = |positions: RangeInclusive<i64>| positions.map(eval).min_by_key(|(_, fuel)| *fuel).unwrap();
+    let (min, max) = (*nums.iter().min().unwrap(), *nums.iter().max().unwrap());
+    match cost.convexity() {
+        Convexity::Linear => eval(*median(nums, MedianPolicy::Lower).start()),
+        Convexity::NearMean => {
+            let mean = nums.iter().sum::<i64>().div_euclid(nums.len() as i64);
+            cheapest(mean - 1..=mean + 2)
+        }
+        Convexity::Convex => eval(slope_search(min, max, |pos| total_fuel(nums, cost, pos))),
+        Convexity::Unknown => cheapest(min..=max),
+    }
+}
+
+// "linear", "triangular", "quadratic", "exponential:base:cap" or "table:fuel,fuel,...",
+// None for costs that go down with the distance
+fn cost_model(name: &str) -> Option<Box<dyn FuelCost>> {
+    let (kind, args) = name.split_once(':').unwrap_or((name, ""));
+    match kind {
+        "linear" => Some(Box::new(Linear)),
+        "triangular" => Some(Box::new(Triangular)),
+        "quadratic" => Some(Box::new(Quadratic)),
+        "exponential" => {
+            let (base, cap) = args.split_once(':')?;
+            let cost = ExponentialCapped { base: base.parse().ok()?, cap: cap.parse().ok()? };
+            (cost.base >= 1 && cost.cap >= 0).then_some(Box::new(cost))
+        }
+        "table" => {
+            let table = Table(args.split(',').map(|n| n.parse()).collect::<Result<_, _>>().ok()?);
+            (!table.0.is_empty() && table.is_increasing()).then_some(Box::new(table))
         }
+        _ => None,
+    }
+}
+
+impl<F: FuelCost + ?Sized> FuelCost for Box<F> {
+    fn fuel(&self, crab: usize, distance: i64) -> i64 {
+        (**self).fuel(crab, distance)
+    }
+
+    fn convexity(&self) -> Convexity {
+        (**self).convexity()
+    }
+}
+
+type XY = (i64, i64);
+
+// How crabs moving on a plane use fuel.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum Metric {
+    // |dx| + |dy|
+    Manhattan,
+    // max(|dx|, |dy|), moving diagonally costs as much as moving straight
+    Chebyshev,
+    // the triangular cost for dx plus the one for dy
+    Triangular,
+}
+
+impl Metric {
+    fn fuel(&self, from: XY, to: XY) -> i64 {
+        let (dx, dy) = (from.0 - to.0, from.1 - to.1);
+        match self {
+            Metric::Manhattan => fuel_cost_linear(0, dx) + fuel_cost_linear(0, dy),
+            Metric::Chebyshev => dx.abs().max(dy.abs()),
+            Metric::Triangular => fuel_cost_nonlinear(0, dx) + fuel_cost_nonlinear(0, dy),
+        }
+    }
+
+    // The cost per axis, when the fuel is the sum of the fuel for x and the fuel for y.
+    fn per_axis(&self) -> Option<Box<dyn FuelCost>> {
+        match self {
+            Metric::Manhattan => Some(Box::new(Linear)),
+            Metric::Chebyshev => None,
+            Metric::Triangular => Some(Box::new(Triangular)),
+        }
+    }
+}
+
+impl FromStr for Metric {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "manhattan" => Ok(Metric::Manhattan),
+            "chebyshev" => Ok(Metric::Chebyshev),
+            "triangular" => Ok(Metric::Triangular),
+            _ => Err(()),
+        }
+    }
+}
+
+pub fn total_fuel_2d(crabs: &[XY], metric: Metric, pos: XY) -> i64 {
+    crabs.iter().map(|crab| metric.fuel(pos, *crab)).sum()
+}
+
+// The best meeting point on the plane and its total fuel.
+pub fn optimize_2d(crabs: &[XY], metric: Metric) -> (XY, i64) {
+    let xs: Vec<i64> = crabs.iter().map(|c| c.0).collect();
+    let ys: Vec<i64> = crabs.iter().map(|c| c.1).collect();
+    if let Some(cost) = metric.per_axis() {
+        let ((x, x_fuel), (y, y_fuel)) = (optimize(&xs, &cost), optimize(&ys, &cost));
+        return ((x, y), x_fuel + y_fuel);
+    }
+
+    // Every x between the crabs, and for each the best y. The fuel is convex along any
+    // line, so for a fixed x the slope search finds that y.
+    let (min_y, max_y) = (*ys.iter().min().unwrap(), *ys.iter().max().unwrap());
+    let (min_x, max_x) = (*xs.iter().min().unwrap(), *xs.iter().max().unwrap());
+    (min_x..=max_x).map(|x| {
+                       let y = slope_search(min_y, max_y, |y| total_fuel_2d(crabs, metric, (x, y)));
+                       ((x, y), total_fuel_2d(crabs, metric, (x, y)))
+                   })
+                   .min_by_key(|(_, fuel)| *fuel)
+                   .unwrap()
+}
+
+// The total fuel for every position from the leftmost to the rightmost crab.
+pub struct CostCurve {
+    pub start: i64,
+    pub fuel: Vec<i64>,
+}
+
+impl CostCurve {
+    pub fn linear(nums: &[i64]) -> CostCurve {
+        CostCurve::sweep(nums, |left_distances, right_distances, _| left_distances + right_distances)
+    }
+
+    pub fn triangular(nums: &[i64]) -> CostCurve {
+        CostCurve::sweep(nums, |_, _, triangular| triangular)
+    }
+
+    // Goes from left to right with the number of crabs left of the position (a prefix sum of
+    // the crabs per position) and the sums of distances to the crabs on either side. Those
+    // change by a count when moving one position, and so does the triangular cost: O(n + range).
+    fn sweep(nums: &[i64], fuel: fn(i64, i64, i64) -> i64) -> CostCurve {
+        let (min, max) = (*nums.iter().min().unwrap(), *nums.iter().max().unwrap());
+        let mut crabs = vec![0; (max - min + 1) as usize];
+        for x in nums {
+            crabs[(x - min) as usize] += 1;
+        }
+
+        // distances to the crabs at or left of the position, and to the ones right of it
+        let mut left_distances = 0;
+        let mut right_distances: i64 = nums.iter().map(|x| x - min).sum();
+        let mut triangular: i64 = nums.iter().map(|x| fuel_cost_nonlinear(min, *x)).sum();
+        let mut left = 0;
+        let mut curve = Vec::with_capacity(crabs.len());
+        for here in crabs {
+            left += here;
+            curve.push(fuel(left_distances, right_distances, triangular));
+            let right = nums.len() as i64 - left;
+            // one step further from every crab on the left and closer to every one on the right
+            triangular += left_distances + left - right_distances;
+            left_distances += left;
+            right_distances -= right;
+        }
+        CostCurve { start: min, fuel: curve }
+    }
+
+    pub fn optimum(&self) -> (i64, i64) {
+        let (i, fuel) = self.fuel.iter().enumerate().min_by_key(|(_, fuel)| **fuel).unwrap();
+        (self.start + i as i64, *fuel)
+    }
+
+    // Both costs are convex, so the positions that cost at most percent % more than the
+    // optimum are all next to each other.
+    pub fn within(&self, percent: f64) -> RangeInclusive<i64> {
+        let (_, best) = self.optimum();
+        // never below the optimum itself, so there's always at least one position
+        let limit = (best as f64 * (1.0 + percent / 100.0)).max(best as f64);
+        let first = self.fuel.iter().position(|f| *f as f64 <= limit).unwrap();
+        let last = self.fuel.iter().rposition(|f| *f as f64 <= limit).unwrap();
+        (self.start + first as i64)..=(self.start + last as i64)
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("position,fuel\n");
+        for (i, fuel) in self.fuel.iter().enumerate() {
+            csv += &format!("{},{}\n", self.start + i as i64, fuel);
+        }
+        csv
     }
 }
 
 fn main() {
+    let mut cost = None;
+    let mut weights = None;
+    let mut metric = None;
+    let mut curve = None;
+    let mut within = 5.0;
+    let mut csv = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--cost" => cost = Some(args.next().and_then(|c| cost_model(&c)).expect("--cost needs a cost model")),
+            // one per crab, comma separated
+            "--weights" => weights = Some(args.next().expect("--weights needs weights")),
+            // crabs on a plane, one x,y per line
+            "--2d" => metric = Some(args.next().and_then(|m| m.parse().ok()).expect("--2d needs a metric")),
+            // the fuel for every position, linear or triangular
+            "--curve" => curve = Some(args.next().expect("--curve needs a cost model")),
+            // how many percent above the optimum still counts as close
+            "--within" => within = args.next()
+                                       .and_then(|w| w.parse().ok())
+                                       .filter(|w: &f64| *w >= 0.0)
+                                       .expect("--within needs a percentage that isn't negative"),
+            // write the curve to a file
+            "--csv" => csv = Some(args.next().expect("--csv needs a file name")),
+            _ => panic!("Unknown argument {}", arg),
+        }
+    }
+
     let mut input = String::new();
 
     io::stdin()
         .read_to_string(&mut input)
         .expect("Failed to read input");
 
-    let nums: Vec<i64> = input.split(",").map(|n| n.parse().unwrap()).collect();
+    if let Some(metric) = metric {
+        let crabs: Vec<XY> = input.lines()
+                                  .filter(|l| !l.trim().is_empty())
+                                  .map(|l| {
+                                      let (x, y) = l.trim().split_once(',').expect("Failed to parse");
+                                      (x.parse().unwrap(), y.parse().unwrap())
+                                  })
+                                  .collect();
+        let ((x, y), fuel) = optimize_2d(&crabs, metric);
+        println!("{} at {},{}", fuel, x, y);
+        return;
+    }
+
+    let nums: Vec<i64> = input.trim().split(",").map(|n| n.parse().unwrap()).collect();
 
-    let result = min_distance2(nums);
+    if let Some(model) = curve {
+        let curve = match model.as_str() {
+            "linear" => CostCurve::linear(&nums),
+            "triangular" => CostCurve::triangular(&nums),
+            _ => panic!("Unknown cost model {}", model),
+        };
+        let (pos, fuel) = curve.optimum();
+        println!("{} at {}", fuel, pos);
+        let close = curve.within(within);
+        println!("Within {}%: {} to {}", within, close.start(), close.end());
+        if let Some(file) = csv {
+            fs::write(&file, curve.to_csv()).expect("Failed to write CSV");
+        }
+        return;
+    }
+
+    let result = match (cost, weights) {
+        (None, None) => min_distance2(nums),
+        (cost, Some(weights)) => {
+            let weights: Vec<i64> = weights.split(',')
+                                           .map(|w| w.trim().parse().ok().filter(|w| *w >= 0))
+                                           .collect::<Option<_>>()
+                                           .expect("Weights need to be numbers that aren't negative");
+            assert!(weights.len() == nums.len(), "{} weights for {} crabs", weights.len(), nums.len());
+            let cost = Weighted { weights, cost: cost.unwrap_or_else(|| Box::new(Triangular)) };
+            optimize(&nums, &cost).1
+        }
+        (Some(cost), None) => optimize(&nums, &cost).1,
+    };
     println!("{}", result);
 }
 
@@ -79,13 +531,167 @@ fn main() {
 mod tests {
     use super::*;
 
+    // Crab positions and weights for the brute-force comparisons, below `max`.
+    fn random(seed: u64) -> impl FnMut(u64) -> u64 {
+        let mut state = seed;
+        move |max| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (state >> 33) % max
+        }
+    }
+
     #[test]
     fn test_min_distance1() {
-        assert_eq!(min_distance1(vec![16,1,2,0,4,2,7,1,2,14]), 37);
+        assert_eq!(min_distance1(vec![16,1,2,0,4,2,7,1,2,14]), (37, 2..=2));
+        assert_eq!(min_distance1(vec![1,9,3,7]), (12, 3..=7));
+        assert_eq!(min_distance1(vec![5]), (0, 5..=5));
     }   
 
+    #[test]
+    fn test_median() {
+        assert_eq!(median(&[3,1,2], MedianPolicy::Lower), 2..=2);
+        assert_eq!(median(&[3,1,2], MedianPolicy::Upper), 2..=2);
+        assert_eq!(median(&[3,1,2], MedianPolicy::Interval), 2..=2);
+        assert_eq!(median(&[4,1,3,2], MedianPolicy::Lower), 2..=2);
+        assert_eq!(median(&[4,1,3,2], MedianPolicy::Upper), 3..=3);
+        assert_eq!(median(&[4,1,3,2], MedianPolicy::Interval), 2..=3);
+
+        // the interval is exactly where the linear cost is lowest
+        let nums = [16,1,2,0,4,2,7,1,14];
+        for extra in 0..20 {
+            let nums: Vec<i64> = nums.iter().copied().chain(std::iter::once(extra)).collect();
+            let cost = |pos: i64| nums.iter().map(|x| fuel_cost_linear(pos, *x)).sum::<i64>();
+            let (least, best) = min_distance1(nums.clone());
+            for pos in -5..25 {
+                assert_eq!(cost(pos) == least, best.contains(&pos), "{:?} {}", nums, pos);
+            }
+        }
+    }
+
     #[test]
     fn test_min_distance2() {
         assert_eq!(min_distance2(vec![16,1,2,0,4,2,7,1,2,14]), 168);
     }   
-}
\ No newline at end of file
+
+    #[test]
+    fn test_nonlinear_against_brute_force() {
+        let crabs = Crabs::new(&[16,1,2,0,4,2,7,1,2,14]);
+        assert_eq!(crabs.best_nonlinear(), (5, 168));
+
+        let mut random = random(7);
+        for _ in 0..500 {
+            let n = random(30) as usize + 1;
+            let spread = random(2000) + 1;
+            let nums: Vec<i64> = (0..n).map(|_| random(spread) as i64 - 500).collect();
+            let crabs = Crabs::new(&nums);
+            let (min, max) = (*nums.iter().min().unwrap(), *nums.iter().max().unwrap());
+            for pos in min - 3..=max + 3 {
+                let brute = |cost: fn(i64, i64) -> i64| nums.iter().map(|x| cost(pos, *x)).sum::<i64>();
+                assert_eq!(crabs.linear_cost(pos), brute(fuel_cost_linear));
+                assert_eq!(crabs.nonlinear_cost(pos), brute(fuel_cost_nonlinear));
+            }
+            let best = (min..=max).map(|pos| nums.iter().map(|x| fuel_cost_nonlinear(pos, *x)).sum())
+                                  .min()
+                                  .unwrap();
+            assert_eq!(crabs.best_nonlinear().1, best, "{:?}", nums);
+        }
+    }
+
+    #[test]
+    fn test_cost_models() {
+        let nums = [16,1,2,0,4,2,7,1,2,14];
+        assert_eq!(optimize(&nums, &Linear), (2, 37));
+        assert_eq!(optimize(&nums, &Triangular), (5, 168));
+        assert_eq!(ExponentialCapped { base: 2, cap: 100 }.fuel(0, 3), 7);
+        assert_eq!(ExponentialCapped { base: 2, cap: 100 }.fuel(0, 300), 100);
+        assert_eq!(Table(vec![0, 1, 3]).fuel(0, 5), 9);
+        assert_eq!(Table(vec![0, 1, 3]).convexity(), Convexity::Convex);
+        assert_eq!(Table(vec![0, 5, 6]).convexity(), Convexity::Unknown);
+        assert_eq!(Table(vec![5, 3, 4]).convexity(), Convexity::Unknown);
+        assert_eq!(optimize(&[4, 5, 7, 4, 2], &Table(vec![5, 3, 4])).1, 19);
+        assert_eq!(Table(vec![]).fuel(0, 3), 0);
+        assert_eq!(ExponentialCapped { base: 0, cap: 5 }.fuel(0, 3), 0);
+        assert!(cost_model("table:5,3,4").is_none());
+        assert!(cost_model("table:").is_none());
+        assert!(cost_model("exponential:0:5").is_none());
+        assert!(cost_model("exponential:2:5").is_some());
+        assert_eq!(Weighted { weights: vec![1, 2], cost: Triangular }.convexity(), Convexity::Convex);
+        assert_eq!(Weighted { weights: vec![1, -2], cost: Triangular }.convexity(), Convexity::Unknown);
+
+        // whatever shortcut the model allows, it finds the same fuel as trying everything
+        let mut random = random(11);
+        for _ in 0..200 {
+            let n = random(20) as usize + 1;
+            let nums: Vec<i64> = (0..n).map(|_| random(60) as i64 - 20).collect();
+            let weights: Vec<i64> = (0..n).map(|_| random(5) as i64 + 1).collect();
+            let models: Vec<Box<dyn FuelCost>> = vec![
+                Box::new(Linear),
+                Box::new(Triangular),
+                Box::new(Quadratic),
+                Box::new(ExponentialCapped { base: 3, cap: 50 }),
+                Box::new(Table(vec![0, 2, 3, 7, 8])),
+                Box::new(Table(vec![0, 1, 3, 6])),
+                Box::new(Weighted { weights: weights.clone(), cost: Linear }),
+                Box::new(Weighted { weights: weights.clone(), cost: Triangular }),
+            ];
+            for model in models.iter() {
+                let (min, max) = (*nums.iter().min().unwrap(), *nums.iter().max().unwrap());
+                let best = (min..=max).map(|pos| total_fuel(&nums, model, pos)).min().unwrap();
+                let (pos, fuel) = optimize(&nums, model);
+                assert_eq!(fuel, best, "{:?} {:?}", nums, model.convexity());
+                assert_eq!(total_fuel(&nums, model, pos), fuel);
+            }
+        }
+    }
+
+    #[test]
+    fn test_two_dimensions() {
+        let crabs = [(0, 0), (4, 0), (0, 4), (4, 4), (2, 9)];
+        assert_eq!(optimize_2d(&crabs, Metric::Manhattan), ((2, 4), 21));
+        assert_eq!(optimize_2d(&crabs, Metric::Chebyshev).1, 15);
+
+        let mut random = random(3);
+        for _ in 0..100 {
+            let n = random(12) as usize + 1;
+            let crabs: Vec<XY> = (0..n).map(|_| (random(25) as i64 - 5, random(25) as i64 - 5)).collect();
+            for metric in [Metric::Manhattan, Metric::Chebyshev, Metric::Triangular] {
+                let best = (-5..20).flat_map(|x| (-5..20).map(move |y| (x, y)))
+                                   .map(|pos| total_fuel_2d(&crabs, metric, pos))
+                                   .min()
+                                   .unwrap();
+                let (pos, fuel) = optimize_2d(&crabs, metric);
+                assert_eq!(fuel, best, "{:?} {:?}", crabs, metric);
+                assert_eq!(total_fuel_2d(&crabs, metric, pos), fuel);
+            }
+        }
+    }
+
+    #[test]
+    fn test_cost_curve() {
+        let nums = [16,1,2,0,4,2,7,1,2,14];
+        let curve = CostCurve::linear(&nums);
+        assert_eq!((curve.start, curve.fuel.len()), (0, 17));
+        assert_eq!(curve.optimum(), (2, 37));
+        assert_eq!(&curve.fuel[..4], &[49, 41, 37, 39]);
+        assert_eq!(curve.within(0.0), 2..=2);
+        assert_eq!(curve.within(-5.0), 2..=2);
+        assert_eq!(curve.within(10.0), 2..=3);
+        assert!(curve.to_csv().starts_with("position,fuel\n0,49\n1,41\n2,37\n"));
+        let curve = CostCurve::triangular(&nums);
+        assert_eq!(curve.optimum(), (5, 168));
+        assert_eq!(curve.within(20.0), 3..=7);
+
+        let mut random = random(5);
+        for _ in 0..200 {
+            let n = random(20) as usize + 1;
+            let nums: Vec<i64> = (0..n).map(|_| random(100) as i64 - 30).collect();
+            for (curve, cost) in [(CostCurve::linear(&nums), fuel_cost_linear as fn(i64, i64) -> i64),
+                                  (CostCurve::triangular(&nums), fuel_cost_nonlinear)] {
+                for (i, fuel) in curve.fuel.iter().enumerate() {
+                    let pos = curve.start + i as i64;
+                    assert_eq!(*fuel, nums.iter().map(|x| cost(pos, *x)).sum::<i64>(), "{:?} {}", nums, pos);
+                }
+            }
+        }
+    }
+}